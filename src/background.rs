@@ -0,0 +1,223 @@
+use crate::{
+    canvas::Canvas,
+    color::Color,
+    consts::PI,
+    encoding::{encode_fields, encode_variants},
+    max,
+    tuple::Tuple,
+    uv_pattern::{CubeFace, ImageTexture, TextureFilter, UvStencil, WrapMode},
+};
+
+/// What a ray sees when it misses every body in the world.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Background {
+    Solid(Color),
+    Gradient(VerticalGradient),
+    Skybox(Skybox),
+    EnvironmentMap(EnvironmentMap),
+}
+
+/// Blends from ```bottom``` straight down to ```top``` straight up.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VerticalGradient {
+    top: Color,
+    bottom: Color,
+}
+
+/// Six images on the inside of a cube around the world.
+/// Faces are ordered as the variants of ```CubeFace```.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Skybox {
+    faces: [ImageTexture; 6],
+}
+
+/// Equirectangular (latitude/longitude) image around the world, the center of the image
+/// looking down +z.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnvironmentMap {
+    texture: ImageTexture,
+    intensity: f64,
+}
+
+impl Background {
+    pub fn color_in_direction(&self, direction: Tuple) -> Color {
+        match self {
+            Background::Solid(color) => *color,
+            Background::Gradient(gradient) => gradient.color_in_direction(direction),
+            Background::Skybox(skybox) => skybox.color_in_direction(direction),
+            Background::EnvironmentMap(map) => map.color_in_direction(direction),
+        }
+    }
+}
+
+impl VerticalGradient {
+    pub fn new(top: Color, bottom: Color) -> Self {
+        Self { top, bottom }
+    }
+
+    /// ```
+    /// use raytracer_rust::background::VerticalGradient;
+    /// use raytracer_rust::color::Color;
+    /// use raytracer_rust::tuple::Tuple;
+    /// let gradient = VerticalGradient::new(Color::WHITE(), Color::BLACK());
+    /// assert_eq!(gradient.color_in_direction(Tuple::Vector(0, 2, 0)), Color::WHITE());
+    /// assert_eq!(gradient.color_in_direction(Tuple::Vector(0, 0, 1)), Color::new(0.5, 0.5, 0.5));
+    /// ```
+    pub fn color_in_direction(&self, direction: Tuple) -> Color {
+        let t = 0.5 * (direction.normalize().y + 1.0);
+        self.bottom * (1.0 - t) + self.top * t
+    }
+}
+
+impl Skybox {
+    pub fn new(faces: [ImageTexture; 6]) -> Self {
+        Self { faces }
+    }
+
+    /// Loads the faces from ppm images ordered as the variants of ```CubeFace```.
+    pub fn load_ppm<T: AsRef<str>>(paths: [T; 6]) -> std::io::Result<Self> {
+        let mut faces = Vec::with_capacity(6);
+        for path in paths {
+            faces.push(ImageTexture::load_ppm(
+                path,
+                TextureFilter::Bilinear,
+                WrapMode::Clamp,
+            )?);
+        }
+        Ok(Self::new(faces.try_into().unwrap()))
+    }
+
+    pub fn color_in_direction(&self, direction: Tuple) -> Color {
+        // push the direction out onto the unit cube
+        let scale = max!(direction.x.abs(), direction.y.abs(), direction.z.abs());
+        let point = Tuple::Point(
+            direction.x / scale,
+            direction.y / scale,
+            direction.z / scale,
+        );
+        let face = CubeFace::of(point);
+        let (u, v) = face.uv(point);
+        self.faces[face as usize].color_at_uv(u, v)
+    }
+}
+
+impl EnvironmentMap {
+    pub fn new(texture: ImageTexture, intensity: f64) -> Self {
+        Self { texture, intensity }
+    }
+
+    pub fn load_hdr<T: AsRef<str>>(load_from: T, intensity: f64) -> std::io::Result<Self> {
+        let image = Canvas::load_hdr(load_from)?;
+        Ok(Self::new(
+            ImageTexture::new(image, TextureFilter::Bilinear, WrapMode::Repeat),
+            intensity,
+        ))
+    }
+
+    pub fn texture(&self) -> &ImageTexture {
+        &self.texture
+    }
+
+    pub fn intensity(&self) -> f64 {
+        self.intensity
+    }
+
+    /// ```
+    /// use raytracer_rust::background::EnvironmentMap;
+    /// use raytracer_rust::tuple::Tuple;
+    /// assert_eq!(EnvironmentMap::direction_to_uv(Tuple::Vector(0, 0, 1)), (0.5, 0.5));
+    /// assert_eq!(EnvironmentMap::direction_to_uv(Tuple::Vector(1, 0, 0)), (0.75, 0.5));
+    /// assert_eq!(EnvironmentMap::direction_to_uv(Tuple::Vector(0, 3, 0)), (0.5, 1.0));
+    /// ```
+    pub fn direction_to_uv(direction: Tuple) -> (f64, f64) {
+        let direction = direction.normalize();
+        let u = 0.5 + direction.x.atan2(direction.z) / (2.0 * PI);
+        let v = 0.5 + direction.y.clamp(-1.0, 1.0).asin() / PI;
+        (u, v)
+    }
+
+    /// Inverse of ```direction_to_uv```.
+    pub fn uv_to_direction(u: f64, v: f64) -> Tuple {
+        let phi = (u - 0.5) * 2.0 * PI;
+        let theta = (v - 0.5) * PI;
+        Tuple::Vector(
+            theta.cos() * phi.sin(),
+            theta.sin(),
+            theta.cos() * phi.cos(),
+        )
+    }
+
+    pub fn color_in_direction(&self, direction: Tuple) -> Color {
+        let (u, v) = Self::direction_to_uv(direction);
+        self.texture.color_at_uv(u, v) * self.intensity
+    }
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Background::Solid(Color::BLACK())
+    }
+}
+
+impl From<Color> for Background {
+    fn from(color: Color) -> Self {
+        Background::Solid(color)
+    }
+}
+
+encode_variants!(Background {
+    0 => Solid,
+    1 => Gradient,
+    2 => Skybox,
+    3 => EnvironmentMap,
+});
+encode_fields!(VerticalGradient { top, bottom });
+encode_fields!(Skybox { faces });
+encode_fields!(EnvironmentMap { texture, intensity });
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn single_color_texture(color: Color) -> ImageTexture {
+        let mut image = Canvas::new(1, 1);
+        image.set_color_at_pixel(0, 0, color);
+        ImageTexture::new(image, TextureFilter::Nearest, WrapMode::Clamp)
+    }
+
+    #[test]
+    fn skybox_picks_the_face_a_direction_points_at() {
+        let skybox = Skybox::new([
+            single_color_texture(Color::RED()),
+            single_color_texture(Color::GREEN()),
+            single_color_texture(Color::BLUE()),
+            single_color_texture(Color::WHITE()),
+            single_color_texture(Color::BLACK()),
+            single_color_texture(Color::new(1.0, 1.0, 0.0)),
+        ]);
+        assert_eq!(
+            skybox.color_in_direction(Tuple::Vector(-5.0, 1.0, 2.0)),
+            Color::RED()
+        );
+        assert_eq!(
+            skybox.color_in_direction(Tuple::Vector(0.1, 0.2, 3.0)),
+            Color::BLUE()
+        );
+        assert_eq!(
+            skybox.color_in_direction(Tuple::Vector(0.0, -1.0, 0.0)),
+            Color::new(1.0, 1.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn environment_map_uv_round_trips() {
+        for direction in [
+            Tuple::Vector(0.3, 0.4, -0.2),
+            Tuple::Vector(-1.0, -0.5, 0.5),
+            Tuple::Vector(0.0, 0.2, 1.0),
+        ] {
+            let (u, v) = EnvironmentMap::direction_to_uv(direction);
+            assert_eq!(EnvironmentMap::uv_to_direction(u, v), direction.normalize());
+        }
+    }
+}
@@ -1,16 +1,31 @@
 use crate::{
+    cone::Cone,
     cube::Cube,
     cylinder::Cylinder,
+    disk::Disk,
     double_cone::DoubleCone,
+    encoding::encode_variants,
+    heightfield::Heightfield,
+    inline_vec::InlineVec,
     intersections::{Intersection, Intersections},
     material::Material,
     matrix::Matrix,
     plane::Plane,
+    quadric::Quadric,
     ray::Ray,
+    sdf::Sdf,
     sphere::Sphere,
+    stats,
+    torus::Torus,
+    transformation::Transformation,
     tuple::Tuple,
 };
 
+/// Hits a single body can report without allocating; more than this spill to the heap.
+pub const INLINE_HITS: usize = 8;
+
+pub type Hits = InlineVec<f64, INLINE_HITS>;
+
 pub trait IntoBody {
     fn into_body(&self) -> Body;
 }
@@ -21,9 +36,17 @@ where
 {
     fn material(&self) -> &Material;
     fn material_mut(&mut self) -> &mut Material;
-    fn transform(&self) -> Matrix<4>;
-    fn transform_mut(&mut self) -> &mut Matrix<4>;
+    fn transformation(&self) -> &Transformation;
+    /// Set a new transform through ```Transformation::set``` so the cached inverses stay in sync.
+    fn transform_mut(&mut self) -> &mut Transformation;
+
+    fn transform(&self) -> Matrix<4> {
+        self.transformation().matrix()
+    }
 
+    /// Sorted distances at which ```ray``` hits the shape within its interval. Intersections
+    /// borrow the ```Body``` they hit, so this returns only the distances; use
+    /// ```Body::intersect``` for full ```Intersections```.
     /// ```
     /// use raytracer_rust::sphere::Sphere;
     /// use raytracer_rust::tuple::Tuple;
@@ -32,20 +55,18 @@ where
     /// let ray = Ray::new(Tuple::Point(0.0, 0.0, -5.0), Tuple::Vector(0.0, 0.0, 1.0));
     /// let sphere = Sphere::default();
     /// let xs = sphere.intersect(&ray);
-    /// assert_eq!(xs.count(), 2);
-    /// assert_eq!(xs[0].t, 4.0);
-    /// assert_eq!(xs[1].t, 6.0);
+    /// assert_eq!(xs.len(), 2);
+    /// assert_eq!(xs[0], 4.0);
+    /// assert_eq!(xs[1], 6.0);
+    /// assert_eq!(sphere.intersect(&ray.with_interval(5.0, 10.0)), vec![6.0]);
     /// ```
-    fn intersect(&self, ray: &Ray) -> Intersections {
-        let object_space_ray = ray.transform(self.transform().inverse());
-        let result = self.intersect_in_object_space(&object_space_ray);
-        Intersections::new(
-            result
-                .into_iter()
-                .map(|t| Intersection::new(t, self.into_body(), *ray))
-                .collect(),
-        )
+    fn intersect(&self, ray: &Ray) -> Hits {
+        let object_space_ray = ray.transform(self.transformation().inverse());
+        let mut hits = self.intersect_in_object_space(&object_space_ray);
+        hits.retain(|t| ray.contains(*t));
+        hits
     }
+
     /// ```
     /// use raytracer_rust::sphere::Sphere;
     /// use raytracer_rust::tuple::Tuple;
@@ -60,15 +81,16 @@ where
     /// assert_eq!(n, Tuple::Vector(0.0, 0.70711, -0.70711));
     /// ```
     fn normal_at(&self, point: Tuple) -> Tuple {
-        let object_point = self.transform().inverse() * point;
+        let object_point = self.transformation().inverse() * point;
         let object_normal = self.normal_at_in_object_space(object_point);
-        let mut world_normal = self.transform().inverse().transpose() * object_normal;
+        let mut world_normal = self.transformation().inverse_transpose() * object_normal;
         world_normal.w = 0.0;
         world_normal.normalize()
     }
 
-    /// Returns the ```sorted``` distances to the intersection points in a vector.
-    fn intersect_in_object_space(&self, object_space_ray: &Ray) -> Vec<f64>;
+    /// Returns the ```sorted``` distances to the intersection points. Hits outside the ray's
+    /// interval are dropped by the caller, but shapes may use it to skip work.
+    fn intersect_in_object_space(&self, object_space_ray: &Ray) -> Hits;
     fn normal_at_in_object_space(&self, object_space_point: Tuple) -> Tuple;
 }
 
@@ -79,6 +101,63 @@ pub enum Body {
     Cube(Cube),
     Cylinder(Cylinder),
     DoubleCone(DoubleCone),
+    Torus(Torus),
+    Cone(Cone),
+    Disk(Disk),
+    Sdf(Sdf),
+    Quadric(Quadric),
+    Heightfield(Heightfield),
+}
+
+impl Body {
+    /// ```
+    /// use raytracer_rust::body::Body;
+    /// use raytracer_rust::sphere::Sphere;
+    /// use raytracer_rust::tuple::Tuple;
+    /// use raytracer_rust::ray::Ray;
+    /// let ray = Ray::new(Tuple::Point(0.0, 0.0, -5.0), Tuple::Vector(0.0, 0.0, 1.0));
+    /// let sphere = Body::from(Sphere::default());
+    /// let xs = sphere.intersect(&ray);
+    /// assert_eq!(xs.count(), 2);
+    /// assert_eq!(xs[0].t, 4.0);
+    /// assert_eq!(xs[1].t, 6.0);
+    /// ```
+    pub fn intersect(&self, ray: &Ray) -> Intersections<'_> {
+        let mut xs = Intersections::default();
+        self.intersect_into(ray, &mut xs);
+        xs
+    }
+
+    /// Whether ```ray``` hits this body anywhere within its interval.
+    /// ```
+    /// use raytracer_rust::body::Body;
+    /// use raytracer_rust::sphere::Sphere;
+    /// use raytracer_rust::tuple::Tuple;
+    /// use raytracer_rust::ray::Ray;
+    /// let ray = Ray::new(Tuple::Point(0.0, 0.0, -5.0), Tuple::Vector(0.0, 0.0, 1.0));
+    /// let sphere = Body::from(Sphere::default());
+    /// assert!(sphere.occludes(&ray.with_interval(0.0, 4.5)));
+    /// assert!(!sphere.occludes(&ray.with_interval(0.0, 3.5)));
+    /// assert!(!sphere.occludes(&ray.with_interval(6.5, f64::INFINITY)));
+    /// ```
+    pub fn occludes(&self, ray: &Ray) -> bool {
+        stats::count_intersection_test(self);
+        let object_space_ray = ray.transform(self.transformation().inverse());
+        self.intersect_in_object_space(&object_space_ray)
+            .iter()
+            .any(|t| ray.contains(*t))
+    }
+
+    /// Adds the intersections with ```ray``` within its interval to ```xs```, unsorted.
+    pub fn intersect_into<'a>(&'a self, ray: &Ray, xs: &mut Intersections<'a>) {
+        stats::count_intersection_test(self);
+        let object_space_ray = ray.transform(self.transformation().inverse());
+        for t in self.intersect_in_object_space(&object_space_ray).iter() {
+            if ray.contains(*t) {
+                xs.insert(Intersection::new(*t, self, *ray));
+            }
+        }
+    }
 }
 
 impl IntoBody for Body {
@@ -95,6 +174,12 @@ impl Intersectable for Body {
             Body::Cube(c) => c.material(),
             Body::Cylinder(c) => c.material(),
             Body::DoubleCone(dc) => dc.material(),
+            Body::Torus(t) => t.material(),
+            Body::Cone(c) => c.material(),
+            Body::Disk(d) => d.material(),
+            Body::Sdf(s) => s.material(),
+            Body::Quadric(q) => q.material(),
+            Body::Heightfield(h) => h.material(),
         }
     }
 
@@ -105,36 +190,60 @@ impl Intersectable for Body {
             Body::Cube(c) => c.material_mut(),
             Body::Cylinder(c) => c.material_mut(),
             Body::DoubleCone(dc) => dc.material_mut(),
+            Body::Torus(t) => t.material_mut(),
+            Body::Cone(c) => c.material_mut(),
+            Body::Disk(d) => d.material_mut(),
+            Body::Sdf(s) => s.material_mut(),
+            Body::Quadric(q) => q.material_mut(),
+            Body::Heightfield(h) => h.material_mut(),
         }
     }
 
-    fn transform(&self) -> Matrix<4> {
+    fn transformation(&self) -> &Transformation {
         match self {
-            Body::Sphere(s) => s.transform(),
-            Body::Plane(p) => p.transform(),
-            Body::Cube(c) => c.transform(),
-            Body::Cylinder(c) => c.transform(),
-            Body::DoubleCone(dc) => dc.transform(),
+            Body::Sphere(s) => s.transformation(),
+            Body::Plane(p) => p.transformation(),
+            Body::Cube(c) => c.transformation(),
+            Body::Cylinder(c) => c.transformation(),
+            Body::DoubleCone(dc) => dc.transformation(),
+            Body::Torus(t) => t.transformation(),
+            Body::Cone(c) => c.transformation(),
+            Body::Disk(d) => d.transformation(),
+            Body::Sdf(s) => s.transformation(),
+            Body::Quadric(q) => q.transformation(),
+            Body::Heightfield(h) => h.transformation(),
         }
     }
 
-    fn transform_mut(&mut self) -> &mut Matrix<4> {
+    fn transform_mut(&mut self) -> &mut Transformation {
         match self {
             Body::Sphere(s) => s.transform_mut(),
             Body::Plane(p) => p.transform_mut(),
             Body::Cube(c) => c.transform_mut(),
             Body::Cylinder(c) => c.transform_mut(),
             Body::DoubleCone(dc) => dc.transform_mut(),
+            Body::Torus(t) => t.transform_mut(),
+            Body::Cone(c) => c.transform_mut(),
+            Body::Disk(d) => d.transform_mut(),
+            Body::Sdf(s) => s.transform_mut(),
+            Body::Quadric(q) => q.transform_mut(),
+            Body::Heightfield(h) => h.transform_mut(),
         }
     }
 
-    fn intersect_in_object_space(&self, ray: &Ray) -> Vec<f64> {
+    fn intersect_in_object_space(&self, ray: &Ray) -> Hits {
         match self {
             Body::Sphere(s) => s.intersect_in_object_space(ray),
             Body::Plane(p) => p.intersect_in_object_space(ray),
             Body::Cube(c) => c.intersect_in_object_space(ray),
             Body::Cylinder(c) => c.intersect_in_object_space(ray),
             Body::DoubleCone(dc) => dc.intersect_in_object_space(ray),
+            Body::Torus(t) => t.intersect_in_object_space(ray),
+            Body::Cone(c) => c.intersect_in_object_space(ray),
+            Body::Disk(d) => d.intersect_in_object_space(ray),
+            Body::Sdf(s) => s.intersect_in_object_space(ray),
+            Body::Quadric(q) => q.intersect_in_object_space(ray),
+            Body::Heightfield(h) => h.intersect_in_object_space(ray),
         }
     }
 
@@ -145,6 +254,26 @@ impl Intersectable for Body {
             Body::Cube(c) => c.normal_at_in_object_space(point),
             Body::Cylinder(c) => c.normal_at_in_object_space(point),
             Body::DoubleCone(dc) => dc.normal_at_in_object_space(point),
+            Body::Torus(t) => t.normal_at_in_object_space(point),
+            Body::Cone(c) => c.normal_at_in_object_space(point),
+            Body::Disk(d) => d.normal_at_in_object_space(point),
+            Body::Sdf(s) => s.normal_at_in_object_space(point),
+            Body::Quadric(q) => q.normal_at_in_object_space(point),
+            Body::Heightfield(h) => h.normal_at_in_object_space(point),
         }
     }
 }
+
+encode_variants!(Body {
+    0 => Sphere,
+    1 => Plane,
+    2 => Cube,
+    3 => Cylinder,
+    4 => DoubleCone,
+    5 => Torus,
+    6 => Cone,
+    7 => Disk,
+    8 => Sdf,
+    9 => Quadric,
+    10 => Heightfield,
+});
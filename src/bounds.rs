@@ -0,0 +1,66 @@
+use crate::{encoding::encode_fields, ray::Ray, tuple::Tuple};
+
+/// Axis aligned box, used to reject rays before running an expensive intersection.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingBox {
+    pub min: Tuple,
+    pub max: Tuple,
+}
+
+impl BoundingBox {
+    pub fn new(min: Tuple, max: Tuple) -> Self {
+        Self { min, max }
+    }
+
+    /// Distances along the ray at which it enters and leaves the box, if it crosses it at all.
+    /// The entry can be negative when the ray starts inside or in front of the box.
+    /// ```
+    /// use raytracer_rust::bounds::BoundingBox;
+    /// use raytracer_rust::ray::Ray;
+    /// use raytracer_rust::tuple::Tuple;
+    /// let bounds = BoundingBox::new(Tuple::Point(-1, -1, -1), Tuple::Point(1, 1, 1));
+    /// let ray = Ray::new(Tuple::Point(0, 0, -5), Tuple::Vector(0, 0, 1));
+    /// assert_eq!(bounds.intersection_range(&ray), Some((4.0, 6.0)));
+    /// let ray = Ray::new(Tuple::Point(2, 0, -5), Tuple::Vector(0, 0, 1));
+    /// assert_eq!(bounds.intersection_range(&ray), None);
+    /// ```
+    pub fn intersection_range(&self, ray: &Ray) -> Option<(f64, f64)> {
+        let mut t_enter = f64::NEG_INFINITY;
+        let mut t_leave = f64::INFINITY;
+        for (origin, direction, min, max) in [
+            (ray.origin.x, ray.direction.x, self.min.x, self.max.x),
+            (ray.origin.y, ray.direction.y, self.min.y, self.max.y),
+            (ray.origin.z, ray.direction.z, self.min.z, self.max.z),
+        ] {
+            if direction == 0.0 {
+                if origin < min || origin > max {
+                    return None;
+                }
+                continue;
+            }
+            let mut t0 = (min - origin) / direction;
+            let mut t1 = (max - origin) / direction;
+            if t0 > t1 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            t_enter = t_enter.max(t0);
+            t_leave = t_leave.min(t1);
+            if t_enter > t_leave {
+                return None;
+            }
+        }
+        Some((t_enter, t_leave))
+    }
+
+    pub fn contains(&self, point: Tuple) -> bool {
+        (self.min.x..=self.max.x).contains(&point.x)
+            && (self.min.y..=self.max.y).contains(&point.y)
+            && (self.min.z..=self.max.z).contains(&point.z)
+    }
+
+    pub fn intersects(&self, ray: &Ray) -> bool {
+        self.intersection_range(ray).is_some()
+    }
+}
+
+encode_fields!(BoundingBox { min, max });
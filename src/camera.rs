@@ -1,19 +1,107 @@
-use crate::{canvas::Canvas, matrix::Matrix, ray::Ray, tuple::Tuple, world::World};
-use rayon::iter::{
-    IndexedParallelIterator, IntoParallelIterator, IntoParallelRefMutIterator, ParallelIterator,
+use crate::{
+    canvas::Canvas,
+    color::Color,
+    encoding::{encode_fields, Encode},
+    matrix::Matrix,
+    ray::Ray,
+    sampler::Sampler,
+    stats::{self, RayKind, RenderStats},
+    transformation::Transformation,
+    tuple::Tuple,
+    world::World,
+};
+use rayon::{
+    iter::{
+        IndexedParallelIterator, IntoParallelIterator, IntoParallelRefMutIterator, ParallelIterator,
+    },
+    slice::ParallelSliceMut,
+    ThreadPool, ThreadPoolBuilder,
+};
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
 };
-use std::sync::Mutex;
 
 pub struct Camera {
-    pub transform: Matrix<4>,
+    pub transform: Transformation,
     pub hsize: usize,
     pub vsize: usize,
     pub field_of_view: f64,
+    /// Width and height in pixels of the tiles ```render_tiled``` hands out to threads.
+    pub tile_size: usize,
+    /// Threads used by ```render_tiled```, ```None``` using rayon's global pool.
+    pub threads: Option<usize>,
+    /// Pool built for ```threads``` on the first render, reused until ```threads``` changes.
+    pool: Mutex<Option<(usize, Arc<ThreadPool>)>>,
     half_width: f64,
     half_height: f64,
     pixel_size: f64,
 }
 
+/// Side in pixels of the blocks that share a single ray in the preview pass of
+/// ```Camera::render_progressive```.
+pub const PREVIEW_BLOCK: usize = 8;
+
+/// Rectangle of pixels within the image, for rendering only part of a frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Region {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Region {
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+/// How far a ```Camera::render_with_progress``` call has got, passed to its callback
+/// every time a tile finishes.
+#[derive(Clone, Copy, Debug)]
+pub struct RenderProgress {
+    pub tiles_done: usize,
+    pub tiles_total: usize,
+    pub elapsed: Duration,
+}
+
+impl RenderProgress {
+    pub fn fraction(&self) -> f64 {
+        self.tiles_done as f64 / self.tiles_total.max(1) as f64
+    }
+
+    /// Time left, assuming the remaining tiles take as long as the finished ones did.
+    pub fn eta(&self) -> Option<Duration> {
+        if self.tiles_done == 0 {
+            return None;
+        }
+        let remaining = (self.tiles_total - self.tiles_done) as f64 / self.tiles_done as f64;
+        Some(self.elapsed.mul_f64(remaining))
+    }
+}
+
+/// Canvas from a render that may have been cancelled, with ```mask[y][x]``` set for every
+/// pixel that was actually traced.
+pub struct PartialRender {
+    pub canvas: Canvas,
+    pub mask: Vec<Vec<bool>>,
+}
+
+impl PartialRender {
+    pub fn is_complete(&self) -> bool {
+        self.mask.iter().flatten().all(|&done| done)
+    }
+}
+
 impl Camera {
     pub fn new(hsize: usize, vsize: usize, field_of_view: f64) -> Self {
         let aspect = hsize as f64 / vsize as f64;
@@ -28,7 +116,10 @@ impl Camera {
             hsize,
             vsize,
             field_of_view,
-            transform: Matrix::Identity(),
+            transform: Transformation::default(),
+            tile_size: 32,
+            threads: None,
+            pool: Mutex::new(None),
             half_width,
             half_height,
             pixel_size,
@@ -50,22 +141,35 @@ impl Camera {
     /// assert_eq!(r.direction, Tuple::Vector(0.66519, 0.33259, -0.66851));
     /// ```
     pub fn ray_for_pixel(&self, px: usize, py: usize) -> Ray {
-        let xoffset = (px as f64 + 0.5) * self.pixel_size;
-        let yoffset = (py as f64 + 0.5) * self.pixel_size;
+        self.ray_for_sample(px, py, 0.5, 0.5)
+    }
+
+    /// Ray through the point ```(dx, dy)``` of a pixel, both offsets in [0, 1) from its
+    /// top left corner.
+    pub fn ray_for_sample(&self, px: usize, py: usize, dx: f64, dy: f64) -> Ray {
+        let xoffset = (px as f64 + dx) * self.pixel_size;
+        let yoffset = (py as f64 + dy) * self.pixel_size;
         let world_x = self.half_width - xoffset;
         let world_y = self.half_height - yoffset;
-        let pixel = self.transform.inverse() * Tuple::Point(world_x, world_y, -1.0);
-        let origin = self.transform.inverse() * Tuple::Point(0, 0, 0);
+        let inverse = self.transform.inverse();
+        let pixel = inverse * Tuple::Point(world_x, world_y, -1.0);
+        let origin = inverse * Tuple::Point(0, 0, 0);
         let direction = (pixel - origin).normalize();
         Ray::new(origin, direction)
     }
 
+    /// Color seen along a primary ray, counted in the render statistics.
+    fn trace(&self, world: &World, ray: Ray) -> Color {
+        stats::count_ray(RayKind::Primary);
+        world.color_at(ray)
+    }
+
     pub fn render(&self, world: &World) -> Canvas {
         let mut canvas = Canvas::new(self.hsize, self.vsize);
         for y in 0..self.vsize {
             for x in 0..self.hsize {
                 let ray = self.ray_for_pixel(x, y);
-                let color = world.color_at(ray);
+                let color = self.trace(world, ray);
                 canvas.set_color_at_pixel(x, y, color);
             }
         }
@@ -77,7 +181,7 @@ impl Camera {
         (0..self.vsize).into_par_iter().for_each(|y| {
             (0..self.hsize).into_par_iter().for_each(|x| {
                 let ray = self.ray_for_pixel(x, y);
-                let color = world.color_at(ray);
+                let color = self.trace(world, ray);
                 canvas.lock().unwrap().set_color_at_pixel(x, y, color);
             })
         });
@@ -89,19 +193,339 @@ impl Camera {
         canvas.par_iter_mut().enumerate().for_each(|(y, row)| {
             row.par_iter_mut().enumerate().for_each(|(x, pixel)| {
                 let ray = self.ray_for_pixel(x, y);
-                let color = world.color_at(ray);
+                let color = self.trace(world, ray);
                 *pixel = color;
             });
         });
         canvas
     }
 
+    /// Renders the image in square tiles that rayon hands out to whichever thread is idle.
+    /// Each tile is traced into a per-thread buffer and then copied into its own slices of
+    /// the canvas rows, so no locking is needed.
+    pub fn render_tiled(&self, world: &World) -> Canvas {
+        self.in_pool(|| self.render_tiles(world, &AtomicBool::new(false), &|_, _| {}))
+    }
+
+    /// Same as ```render_tiled```, but also returns what was traced and how long tracing the
+    /// tiles and copying them onto the canvas took. Every thread counts into its own
+    /// counters, which are merged once per tile.
+    /// ```
+    /// use raytracer_rust::camera::Camera;
+    /// use raytracer_rust::world::World;
+    ///
+    /// let camera = Camera::new(16, 8, 1.0);
+    /// let (_, stats) = camera.render_with_stats(&World::default_from_book());
+    /// assert_eq!(stats.primary_rays, 16 * 8);
+    /// let phases: Vec<_> = stats.phases.iter().map(|(phase, _)| *phase).collect();
+    /// assert_eq!(phases, ["trace", "assemble"]);
+    /// ```
+    pub fn render_with_stats(&self, world: &World) -> (Canvas, RenderStats) {
+        let stats = Mutex::new(RenderStats::default());
+        let canvas = self.in_pool(|| {
+            self.render_tiles(world, &AtomicBool::new(false), &|_, tile_stats| {
+                stats.lock().unwrap().merge(&tile_stats)
+            })
+        });
+        (canvas, stats.into_inner().unwrap())
+    }
+
+    /// Same as ```render_tiled```, but calls ```progress``` after every finished tile and
+    /// stops handing out tiles once ```cancel``` is set. Tiles already being traced are
+    /// finished, everything else is left black and unset in the mask.
+    /// ```
+    /// use raytracer_rust::camera::Camera;
+    /// use raytracer_rust::world::World;
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    ///
+    /// let camera = Camera::new(64, 64, 1.0).with_tile_size(16).with_threads(1);
+    /// let cancel = AtomicBool::new(false);
+    /// let render = camera.render_with_progress(
+    ///     &World::default_from_book(),
+    ///     |progress| {
+    ///         if progress.tiles_done == 4 {
+    ///             cancel.store(true, Ordering::Relaxed);
+    ///         }
+    ///     },
+    ///     &cancel,
+    /// );
+    /// assert!(!render.is_complete());
+    /// assert_eq!(render.mask.iter().flatten().filter(|&&done| done).count(), 4 * 16 * 16);
+    /// ```
+    pub fn render_with_progress(
+        &self,
+        world: &World,
+        progress: impl Fn(RenderProgress) + Sync,
+        cancel: &AtomicBool,
+    ) -> PartialRender {
+        let tile_size = self.tile_size.max(1);
+        let columns = self.hsize.div_ceil(tile_size);
+        let tiles: Vec<AtomicBool> = (0..columns * self.vsize.div_ceil(tile_size))
+            .map(|_| AtomicBool::new(false))
+            .collect();
+        let tiles_done = AtomicUsize::new(0);
+        let start = Instant::now();
+        let canvas = self.in_pool(|| {
+            self.render_tiles(world, cancel, &|tile, _| {
+                tiles[tile].store(true, Ordering::Relaxed);
+                progress(RenderProgress {
+                    tiles_done: tiles_done.fetch_add(1, Ordering::Relaxed) + 1,
+                    tiles_total: tiles.len(),
+                    elapsed: start.elapsed(),
+                });
+            })
+        });
+        let mask = (0..self.vsize)
+            .map(|y| {
+                (0..self.hsize)
+                    .map(|x| tiles[y / tile_size * columns + x / tile_size].load(Ordering::Relaxed))
+                    .collect()
+            })
+            .collect();
+        PartialRender { canvas, mask }
+    }
+
+    /// Renders ```passes``` refinement passes after a quick preview, calling ```on_pass```
+    /// with the pass number and the image so far after each one. Pass 0 traces one ray per
+    /// ```PREVIEW_BLOCK``` square block, pass 1 one ray through every pixel center, and
+    /// every later pass adds a jittered sample per pixel to the running average. Jitter
+    /// is seeded by ```seed```, the pass and the pixel, so runs with the same seed match.
+    pub fn render_progressive(
+        &self,
+        world: &World,
+        passes: usize,
+        seed: u64,
+        mut on_pass: impl FnMut(usize, &Canvas),
+    ) -> Canvas {
+        let mut canvas = Canvas::new(self.hsize, self.vsize);
+        let preview: Vec<Vec<Color>> = self.in_pool(|| {
+            (0..self.vsize.div_ceil(PREVIEW_BLOCK))
+                .into_par_iter()
+                .map(|by| {
+                    (0..self.hsize.div_ceil(PREVIEW_BLOCK))
+                        .map(|bx| {
+                            self.trace(
+                                world,
+                                self.ray_for_pixel(bx * PREVIEW_BLOCK, by * PREVIEW_BLOCK),
+                            )
+                        })
+                        .collect()
+                })
+                .collect()
+        });
+        for y in 0..self.vsize {
+            for x in 0..self.hsize {
+                canvas.set_color_at_pixel(x, y, preview[y / PREVIEW_BLOCK][x / PREVIEW_BLOCK]);
+            }
+        }
+        on_pass(0, &canvas);
+
+        for pass in 1..=passes {
+            let samples: Vec<Vec<Color>> = self.in_pool(|| {
+                (0..self.vsize)
+                    .into_par_iter()
+                    .map(|y| {
+                        (0..self.hsize)
+                            .map(|x| {
+                                let (dx, dy) = if pass == 1 {
+                                    (0.5, 0.5)
+                                } else {
+                                    let mut sampler = Sampler::from_seeds(&[
+                                        seed,
+                                        pass as u64,
+                                        x as u64,
+                                        y as u64,
+                                    ]);
+                                    (sampler.next_f64(), sampler.next_f64())
+                                };
+                                self.trace(world, self.ray_for_sample(x, y, dx, dy))
+                            })
+                            .collect()
+                    })
+                    .collect()
+            });
+            // every pass adds one sample per pixel, so the running mean weighs the new one
+            // by 1 / pass, and pass 1 replaces the preview outright
+            for (y, row) in samples.into_iter().enumerate() {
+                for (x, color) in row.into_iter().enumerate() {
+                    let average = canvas.color_at_pixel(x, y);
+                    canvas.set_color_at_pixel(
+                        x,
+                        y,
+                        average + (color - average) * (1.0 / pass as f64),
+                    );
+                }
+            }
+            on_pass(pass, &canvas);
+        }
+        canvas
+    }
+
+    /// Renders only the pixels of ```region``` into a canvas of the region's size. Every
+    /// pixel is traced with the same ray as in a full render, so regions can be rendered
+    /// separately and stitched together with ```Canvas::paste```.
+    /// ```
+    /// use raytracer_rust::camera::{Camera, Region};
+    /// use raytracer_rust::world::World;
+    ///
+    /// let world = World::default_from_book();
+    /// let camera = Camera::new(8, 6, 1.0);
+    /// let crop = camera.render_region(&world, Region::new(2, 1, 4, 3));
+    /// assert_eq!(crop.color_at_pixel(1, 1), camera.render(&world).color_at_pixel(3, 2));
+    /// ```
+    pub fn render_region(&self, world: &World, region: Region) -> Canvas {
+        assert!(
+            region.x + region.width <= self.hsize && region.y + region.height <= self.vsize,
+            "{region:?} is outside the {}x{} image",
+            self.hsize,
+            self.vsize
+        );
+        let mut canvas = Canvas::new(region.width, region.height);
+        self.in_pool(|| {
+            canvas.par_iter_mut().enumerate().for_each(|(y, row)| {
+                for (x, pixel) in row.iter_mut().enumerate() {
+                    let ray = self.ray_for_pixel(region.x + x, region.y + y);
+                    *pixel = self.trace(world, ray);
+                }
+            })
+        });
+        canvas
+    }
+
+    /// Renders the pixels of ```region``` into a full size ```canvas```, leaving the
+    /// rest of it untouched.
+    pub fn render_region_into(&self, world: &World, region: Region, canvas: &mut Canvas) {
+        canvas.paste(region.x, region.y, &self.render_region(world, region));
+    }
+
+    fn in_pool<R: Send>(&self, render: impl FnOnce() -> R + Send) -> R {
+        match self.threads {
+            Some(threads) => self.thread_pool(threads).install(render),
+            None => render(),
+        }
+    }
+
+    fn thread_pool(&self, threads: usize) -> Arc<ThreadPool> {
+        let mut pool = self.pool.lock().unwrap();
+        match pool.as_ref() {
+            Some((built_for, pool)) if *built_for == threads => pool.clone(),
+            _ => {
+                let built = Arc::new(
+                    ThreadPoolBuilder::new()
+                        .num_threads(threads)
+                        .build()
+                        .expect("failed to start the render threads"),
+                );
+                *pool = Some((threads, built.clone()));
+                built
+            }
+        }
+    }
+
+    /// Traces every tile unless ```cancel``` is set, calling ```finished``` with the row
+    /// major index of each tile and what tracing it took once it is on the canvas.
+    fn render_tiles(
+        &self,
+        world: &World,
+        cancel: &AtomicBool,
+        finished: &(dyn Fn(usize, RenderStats) + Sync),
+    ) -> Canvas {
+        let tile_size = self.tile_size.max(1);
+        let columns = self.hsize.div_ceil(tile_size);
+        let mut canvas = Canvas::new(self.hsize, self.vsize);
+        canvas
+            .rows_mut()
+            .par_chunks_mut(tile_size)
+            .enumerate()
+            .for_each(|(band, rows)| {
+                // give every tile in this band of rows its own slice of each row
+                let height = rows.len();
+                let mut tiles: Vec<Vec<&mut [Color]>> = vec![];
+                for row in rows.iter_mut() {
+                    for (column, slice) in row.chunks_mut(tile_size).enumerate() {
+                        if column == tiles.len() {
+                            tiles.push(Vec::with_capacity(height));
+                        }
+                        tiles[column].push(slice);
+                    }
+                }
+                tiles.into_par_iter().enumerate().for_each_init(
+                    || Vec::with_capacity(tile_size * tile_size),
+                    |buffer, (column, mut tile)| {
+                        if cancel.load(Ordering::Relaxed) {
+                            return;
+                        }
+                        let (x0, y0) = (column * tile_size, band * tile_size);
+                        let width = tile[0].len();
+                        buffer.clear();
+                        let start = Instant::now();
+                        let (_, mut tile_stats) = stats::measure(|| {
+                            for y in y0..y0 + height {
+                                for x in x0..x0 + width {
+                                    buffer.push(self.trace(world, self.ray_for_pixel(x, y)));
+                                }
+                            }
+                        });
+                        tile_stats.phases.push(("trace", start.elapsed()));
+                        let start = Instant::now();
+                        for (row, colors) in tile.iter_mut().zip(buffer.chunks(width)) {
+                            row.copy_from_slice(colors);
+                        }
+                        tile_stats.phases.push(("assemble", start.elapsed()));
+                        finished(band * columns + column, tile_stats);
+                    },
+                );
+            });
+        canvas
+    }
+
+    pub fn with_tile_size(mut self, tile_size: usize) -> Self {
+        self.tile_size = tile_size;
+        self
+    }
+
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    pub fn set_transform(&mut self, transform: Matrix<4>) {
+        self.transform.set(transform);
+    }
+
     pub fn look_at_from_position(mut self, from: Tuple, to: Tuple, up: Tuple) -> Self {
-        self.transform = Matrix::view_transform(from, to, up);
+        self.set_transform(Matrix::view_transform(from, to, up));
         self
     }
 }
 
+/// Sends the view, not the render settings, which are up to whoever renders it.
+impl Encode for Camera {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.hsize.encode(out);
+        self.vsize.encode(out);
+        self.field_of_view.encode(out);
+        self.transform.encode(out);
+    }
+
+    fn decode(bytes: &mut &[u8]) -> std::io::Result<Self> {
+        let mut camera = Camera::new(
+            usize::decode(bytes)?,
+            usize::decode(bytes)?,
+            f64::decode(bytes)?,
+        );
+        camera.transform = Transformation::decode(bytes)?;
+        Ok(camera)
+    }
+}
+
+encode_fields!(Region {
+    x,
+    y,
+    width,
+    height
+});
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -114,6 +538,16 @@ mod tests {
         assert_eq!(ray.direction, Tuple::Vector(0.66519, 0.33259, -0.66851));
     }
 
+    #[test]
+    fn only_rendering_counts_primary_rays() {
+        let world = World::default_from_book();
+        let camera = Camera::new(6, 4, 1.0);
+        let (_, counted) = stats::measure(|| camera.ray_for_pixel(0, 0));
+        assert_eq!(counted.primary_rays, 0);
+        let (_, counted) = stats::measure(|| camera.render(&world));
+        assert_eq!(counted.primary_rays, 24);
+    }
+
     #[test]
     fn render() {
         let world = World::default_from_book();
@@ -121,11 +555,168 @@ mod tests {
         let from = Tuple::Point(0, 0, -5);
         let to = Tuple::Point(0, 0, 0);
         let up = Tuple::Vector(0, 1, 0);
-        camera.transform = Matrix::view_transform(from, to, up);
+        camera.set_transform(Matrix::view_transform(from, to, up));
         let image = camera.render(&world);
         assert_eq!(
             image.color_at_pixel(5, 5),
             crate::color::Color::new(0.38066, 0.47583, 0.2855)
         );
     }
+
+    #[test]
+    fn tiled_render_matches_sequential_render() {
+        let world = World::default_from_book();
+        let camera = Camera::new(13, 9, crate::consts::PI_BY_2)
+            .look_at_from_position(
+                Tuple::Point(0, 0, -5),
+                Tuple::Point(0, 0, 0),
+                Tuple::Vector(0, 1, 0),
+            )
+            .with_tile_size(4)
+            .with_threads(3);
+        let expected = camera.render(&world);
+        let image = camera.render_tiled(&world);
+        for y in 0..9 {
+            for x in 0..13 {
+                assert_eq!(image.color_at_pixel(x, y), expected.color_at_pixel(x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn renders_reuse_the_thread_pool() {
+        let mut camera = Camera::new(4, 4, 1.0).with_threads(2);
+        let pool = camera.thread_pool(2);
+        camera.render_progressive(&World::default_from_book(), 3, 0, |_, _| {});
+        assert!(Arc::ptr_eq(&pool, &camera.thread_pool(2)));
+        camera.threads = Some(3);
+        camera.render_tiled(&World::default_from_book());
+        let rebuilt = camera.thread_pool(3);
+        assert!(!Arc::ptr_eq(&pool, &rebuilt));
+        assert_eq!(rebuilt.current_num_threads(), 3);
+    }
+
+    #[test]
+    fn cancelled_render_keeps_finished_tiles() {
+        let world = World::default_from_book();
+        let camera = Camera::new(10, 10, crate::consts::PI_BY_2)
+            .look_at_from_position(
+                Tuple::Point(0, 0, -5),
+                Tuple::Point(0, 0, 0),
+                Tuple::Vector(0, 1, 0),
+            )
+            .with_tile_size(5)
+            .with_threads(1);
+        let expected = camera.render(&world);
+
+        let cancel = AtomicBool::new(false);
+        let reports = Mutex::new(vec![]);
+        let partial = camera.render_with_progress(
+            &world,
+            |progress| {
+                reports.lock().unwrap().push(progress);
+                if progress.tiles_done == 3 {
+                    cancel.store(true, Ordering::Relaxed);
+                }
+            },
+            &cancel,
+        );
+        let reports = reports.into_inner().unwrap();
+        assert_eq!(reports.len(), 3);
+        assert_eq!(reports[2].tiles_total, 4);
+        assert_eq!(reports[2].fraction(), 0.75);
+        assert!(reports[2].eta().is_some());
+        assert!(!partial.is_complete());
+        for y in 0..10 {
+            for x in 0..10 {
+                let color = if partial.mask[y][x] {
+                    expected.color_at_pixel(x, y)
+                } else {
+                    Color::new(0.0, 0.0, 0.0)
+                };
+                assert_eq!(partial.canvas.color_at_pixel(x, y), color);
+            }
+        }
+
+        let full = camera.render_with_progress(&world, |_| {}, &AtomicBool::new(false));
+        assert!(full.is_complete());
+    }
+
+    #[test]
+    fn progressive_render_refines_a_preview_deterministically() {
+        let world = World::default_from_book();
+        let camera = Camera::new(20, 12, crate::consts::PI_BY_2).look_at_from_position(
+            Tuple::Point(0, 0, -5),
+            Tuple::Point(0, 0, 0),
+            Tuple::Vector(0, 1, 0),
+        );
+        let expected = camera.render(&world);
+        let mut passes = vec![];
+        let image = camera.render_progressive(&world, 3, 42, |pass, canvas| {
+            passes.push((pass, canvas.clone()));
+        });
+        assert_eq!(
+            passes.iter().map(|(pass, _)| *pass).collect::<Vec<_>>(),
+            [0, 1, 2, 3]
+        );
+
+        let preview = &passes[0].1;
+        assert_eq!(preview.color_at_pixel(7, 7), expected.color_at_pixel(0, 0));
+        assert_eq!(
+            preview.color_at_pixel(19, 11),
+            expected.color_at_pixel(16, 8)
+        );
+        for y in 0..12 {
+            for x in 0..20 {
+                assert_eq!(
+                    passes[1].1.color_at_pixel(x, y),
+                    expected.color_at_pixel(x, y)
+                );
+            }
+        }
+
+        // pass 2 averages the centre sample of pass 1 with one jittered sample
+        let mut sampler = Sampler::from_seeds(&[42, 2, 5, 5]);
+        let (dx, dy) = (sampler.next_f64(), sampler.next_f64());
+        let jittered = world.color_at(camera.ray_for_sample(5, 5, dx, dy));
+        assert_eq!(
+            passes[2].1.color_at_pixel(5, 5),
+            (expected.color_at_pixel(5, 5) + jittered) * 0.5
+        );
+
+        let again = camera.render_progressive(&world, 3, 42, |_, _| {});
+        assert_eq!(image, again);
+    }
+
+    #[test]
+    fn regions_stitch_into_the_full_render() {
+        let world = World::default_from_book();
+        let camera = Camera::new(15, 10, crate::consts::PI_BY_2).look_at_from_position(
+            Tuple::Point(0, 0, -5),
+            Tuple::Point(0, 0, 0),
+            Tuple::Vector(0, 1, 0),
+        );
+        let expected = camera.render(&world);
+
+        let mut stitched = Canvas::new(15, 10);
+        for region in [
+            Region::new(0, 0, 15, 4),
+            Region::new(0, 4, 6, 6),
+            Region::new(6, 4, 9, 6),
+        ] {
+            let crop = camera.render_region(&world, region);
+            stitched.paste(region.x, region.y, &crop);
+        }
+        assert_eq!(stitched, expected);
+
+        let marker = Color::new(0.1, 0.2, 0.3);
+        let mut canvas = Canvas::new(15, 10);
+        canvas.set_color_at_pixel(0, 0, marker);
+        canvas.set_color_at_pixel(14, 9, marker);
+        camera.render_region_into(&world, Region::new(5, 3, 4, 4), &mut canvas);
+        assert_eq!(canvas.color_at_pixel(0, 0), marker);
+        assert_eq!(canvas.color_at_pixel(14, 9), marker);
+        assert_eq!(canvas.color_at_pixel(4, 3), Color::BLACK());
+        assert_eq!(canvas.color_at_pixel(7, 5), expected.color_at_pixel(7, 5));
+    }
 }
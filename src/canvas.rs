@@ -1,6 +1,14 @@
-use crate::color::{Color, ToRGB};
+use crate::{
+    color::{Color, ToRGB},
+    encoding::{invalid, Encode},
+};
 use rayon::iter::IntoParallelIterator;
-use std::{convert::AsRef, fs, path::Path};
+use std::{
+    convert::AsRef,
+    fs,
+    io::{Error, ErrorKind},
+    path::Path,
+};
 
 pub trait Sized {
     fn width(&self) -> usize;
@@ -27,6 +35,7 @@ pub trait ToPPM: Sized {
     }
 }
 
+#[derive(Clone, Debug, PartialEq)]
 pub struct Canvas {
     width: usize,
     height: usize,
@@ -46,6 +55,245 @@ impl Canvas {
         }
     }
 
+    pub fn load_ppm<T: AsRef<str>>(load_from: T) -> std::io::Result<Self> {
+        Self::from_ppm(&fs::read(Path::new(load_from.as_ref()))?)
+    }
+
+    /// Parses a plain (P3) or binary (P6) ppm image.
+    /// ```
+    /// use raytracer_rust::canvas::Canvas;
+    /// use raytracer_rust::color::Color;
+    /// let ppm = "P3\n# a comment\n2 1\n255\n255 0 0  0 0 255\n";
+    /// let canvas = Canvas::from_ppm(ppm.as_bytes()).unwrap();
+    /// assert_eq!(canvas.color_at_pixel(0, 0), Color::RED());
+    /// assert_eq!(canvas.color_at_pixel(1, 0), Color::BLUE());
+    /// assert!(Canvas::from_ppm(b"P5\n1 1\n255\n0").is_err());
+    /// ```
+    pub fn from_ppm(bytes: &[u8]) -> std::io::Result<Self> {
+        let invalid = |msg: &str| Error::new(ErrorKind::InvalidData, msg.to_string());
+        let mut pos = 0;
+        // Header fields are whitespace separated and may be interleaved with comments.
+        let next_token = |pos: &mut usize| -> std::io::Result<String> {
+            loop {
+                while *pos < bytes.len() && bytes[*pos].is_ascii_whitespace() {
+                    *pos += 1;
+                }
+                if *pos < bytes.len() && bytes[*pos] == b'#' {
+                    while *pos < bytes.len() && bytes[*pos] != b'\n' {
+                        *pos += 1;
+                    }
+                } else {
+                    break;
+                }
+            }
+            let start = *pos;
+            while *pos < bytes.len() && !bytes[*pos].is_ascii_whitespace() {
+                *pos += 1;
+            }
+            if start == *pos {
+                return Err(invalid("unexpected end of ppm data"));
+            }
+            Ok(String::from_utf8_lossy(&bytes[start..*pos]).into_owned())
+        };
+        let next_number = |pos: &mut usize| -> std::io::Result<usize> {
+            next_token(pos)?
+                .parse()
+                .map_err(|_| invalid("expected a number in ppm data"))
+        };
+
+        let magic = next_token(&mut pos)?;
+        if magic != "P3" && magic != "P6" {
+            return Err(invalid("only P3 and P6 ppm images are supported"));
+        }
+        let width = next_number(&mut pos)?;
+        let height = next_number(&mut pos)?;
+        let max_value = next_number(&mut pos)?;
+        if max_value == 0 || max_value > 65535 {
+            return Err(invalid("invalid maximum color value in ppm data"));
+        }
+        if width == 0 || height == 0 {
+            return Err(invalid("ppm image has no pixels"));
+        }
+        // a plain value takes at least one byte and a binary one at least one or two, so a
+        // corrupt size is caught here instead of allocating a huge canvas
+        let bytes_per_value = if magic == "P6" && max_value >= 256 {
+            2
+        } else {
+            1
+        };
+        let raster_len = width
+            .checked_mul(height)
+            .and_then(|n| n.checked_mul(3 * bytes_per_value));
+        if raster_len.is_none_or(|len| len > bytes.len() - pos) {
+            return Err(invalid("ppm raster is shorter than its header claims"));
+        }
+
+        let mut canvas = Canvas::new(width, height);
+        let scale = max_value as f64;
+        if magic == "P3" {
+            for y in 0..height {
+                for x in 0..width {
+                    let r = next_number(&mut pos)? as f64 / scale;
+                    let g = next_number(&mut pos)? as f64 / scale;
+                    let b = next_number(&mut pos)? as f64 / scale;
+                    canvas.set_color_at_pixel(x, y, Color::new(r, g, b));
+                }
+            }
+        } else {
+            // a single whitespace separates the header from the raster
+            pos += 1;
+            let raster = bytes.get(pos..).unwrap_or_default();
+            if raster.len() < width * height * 3 * bytes_per_value {
+                return Err(invalid("ppm raster is shorter than its header claims"));
+            }
+            let mut values = raster.chunks(bytes_per_value).map(|value| {
+                value
+                    .iter()
+                    .fold(0usize, |acc, byte| (acc << 8) | *byte as usize) as f64
+                    / scale
+            });
+            for y in 0..height {
+                for x in 0..width {
+                    let (r, g, b) = (
+                        values.next().unwrap(),
+                        values.next().unwrap(),
+                        values.next().unwrap(),
+                    );
+                    canvas.set_color_at_pixel(x, y, Color::new(r, g, b));
+                }
+            }
+        }
+        Ok(canvas)
+    }
+
+    pub fn load_hdr<T: AsRef<str>>(load_from: T) -> std::io::Result<Self> {
+        Self::from_hdr(&fs::read(Path::new(load_from.as_ref()))?)
+    }
+
+    /// Parses a Radiance RGBE (.hdr) image, either flat or run-length encoded.
+    /// Colors are not clamped, so they can be well above 1.0.
+    pub fn from_hdr(bytes: &[u8]) -> std::io::Result<Self> {
+        let invalid = |msg: &str| Error::new(ErrorKind::InvalidData, msg.to_string());
+        let mut pos = 0;
+        let next_line = |pos: &mut usize| -> std::io::Result<String> {
+            let start = *pos;
+            while *pos < bytes.len() && bytes[*pos] != b'\n' {
+                *pos += 1;
+            }
+            if *pos >= bytes.len() {
+                return Err(invalid("unexpected end of hdr header"));
+            }
+            *pos += 1;
+            Ok(String::from_utf8_lossy(&bytes[start..*pos - 1]).into_owned())
+        };
+
+        let magic = next_line(&mut pos)?;
+        if !magic.starts_with("#?") {
+            return Err(invalid("missing radiance hdr signature"));
+        }
+        loop {
+            let line = next_line(&mut pos)?;
+            if line.is_empty() {
+                break;
+            }
+            if line.starts_with("FORMAT=") && line != "FORMAT=32-bit_rle_rgbe" {
+                return Err(invalid("only rgbe hdr images are supported"));
+            }
+        }
+        let resolution = next_line(&mut pos)?;
+        let fields: Vec<&str> = resolution.split_whitespace().collect();
+        let (height, width): (usize, usize) = match fields[..] {
+            ["-Y", height, "+X", width] => (
+                height.parse().map_err(|_| invalid("invalid hdr height"))?,
+                width.parse().map_err(|_| invalid("invalid hdr width"))?,
+            ),
+            _ => return Err(invalid("only -Y h +X w hdr orientation is supported")),
+        };
+        if width == 0 || height == 0 {
+            return Err(invalid("hdr image has no pixels"));
+        }
+        // run-length encoded scanlines take at least a header and one run per 127 pixels of
+        // each component, flat ones four bytes per pixel
+        let min_scanline_len = if (8..0x8000).contains(&width) {
+            4 + 8 * width.div_ceil(127)
+        } else {
+            width.saturating_mul(4)
+        };
+        if min_scanline_len
+            .checked_mul(height)
+            .is_none_or(|len| len > bytes.len() - pos)
+        {
+            return Err(invalid("hdr data is shorter than its header claims"));
+        }
+
+        let mut canvas = Canvas::new(width, height);
+        let mut scanline = vec![[0u8; 4]; width];
+        for y in 0..height {
+            let header = bytes.get(pos..pos + 4).unwrap_or_default();
+            let is_rle = (8..0x8000).contains(&width)
+                && header.len() == 4
+                && header[0] == 2
+                && header[1] == 2
+                && ((header[2] as usize) << 8 | header[3] as usize) == width;
+            if is_rle {
+                pos += 4;
+                // each of the four components is run-length encoded separately
+                for component in 0..4 {
+                    let mut x = 0;
+                    while x < width {
+                        let count = *bytes.get(pos).ok_or_else(|| invalid("truncated hdr"))?;
+                        pos += 1;
+                        if count > 128 {
+                            let run = (count - 128) as usize;
+                            let value = *bytes.get(pos).ok_or_else(|| invalid("truncated hdr"))?;
+                            pos += 1;
+                            if x + run > width {
+                                return Err(invalid("hdr run overflows its scanline"));
+                            }
+                            scanline[x..x + run]
+                                .iter_mut()
+                                .for_each(|pixel| pixel[component] = value);
+                            x += run;
+                        } else {
+                            let run = count as usize;
+                            if run == 0 || x + run > width {
+                                return Err(invalid("invalid hdr run length"));
+                            }
+                            let values = bytes
+                                .get(pos..pos + run)
+                                .ok_or_else(|| invalid("truncated hdr"))?;
+                            pos += run;
+                            scanline[x..x + run]
+                                .iter_mut()
+                                .zip(values)
+                                .for_each(|(pixel, value)| pixel[component] = *value);
+                            x += run;
+                        }
+                    }
+                }
+            } else {
+                let values = bytes
+                    .get(pos..pos + width * 4)
+                    .ok_or_else(|| invalid("truncated hdr"))?;
+                pos += width * 4;
+                scanline
+                    .iter_mut()
+                    .zip(values.chunks(4))
+                    .for_each(|(pixel, value)| pixel.copy_from_slice(value));
+            }
+            for (x, [r, g, b, e]) in scanline.iter().enumerate() {
+                let color = if *e == 0 {
+                    Color::BLACK()
+                } else {
+                    let scale = 2f64.powi(*e as i32 - (128 + 8));
+                    Color::new(*r as f64 * scale, *g as f64 * scale, *b as f64 * scale)
+                };
+                canvas.set_color_at_pixel(x, y, color);
+            }
+        }
+        Ok(canvas)
+    }
+
     pub fn set_color_at_pixel(&mut self, x: usize, y: usize, color: Color) {
         self.frame[y][x] = color;
     }
@@ -54,10 +302,30 @@ impl Canvas {
         self.frame[y][x]
     }
 
+    /// Copies ```other``` onto this canvas with its top left corner at ```(x, y)```,
+    /// e.g. to stitch separately rendered regions into one image.
+    pub fn paste(&mut self, x: usize, y: usize, other: &Canvas) {
+        assert!(
+            x + other.width <= self.width && y + other.height <= self.height,
+            "a {}x{} canvas does not fit at ({x}, {y}) of a {}x{} canvas",
+            other.width,
+            other.height,
+            self.width,
+            self.height
+        );
+        for (row, other_row) in self.frame[y..].iter_mut().zip(&other.frame) {
+            row[x..x + other.width].copy_from_slice(other_row);
+        }
+    }
+
     pub fn iter(&self) -> std::slice::Iter<Vec<Color>> {
         self.frame.iter()
     }
 
+    pub fn rows_mut(&mut self) -> &mut [Vec<Color>] {
+        &mut self.frame
+    }
+
     // For debugging purposes.
     pub fn color_center(mut self, color: Color) -> Self {
         for y in (self.height / 2 - 5)..(self.height / 2 + 6) {
@@ -146,3 +414,91 @@ impl<'data> IntoParallelIterator for &'data mut Canvas {
         <&mut [Vec<Color>]>::into_par_iter(self.frame.as_mut_slice())
     }
 }
+
+impl Encode for Canvas {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.width.encode(out);
+        self.height.encode(out);
+        for color in self.frame.iter().flatten() {
+            color.encode(out);
+        }
+    }
+
+    fn decode(bytes: &mut &[u8]) -> std::io::Result<Self> {
+        let width = usize::decode(bytes)?;
+        let height = usize::decode(bytes)?;
+        // 24 bytes per pixel, checked up front so a corrupt size cannot allocate a huge canvas
+        let len = width.checked_mul(height).and_then(|n| n.checked_mul(24));
+        if len.is_none_or(|len| len > bytes.len()) {
+            return Err(invalid("canvas size does not match the encoded pixels"));
+        }
+        let mut canvas = Canvas::new(width, height);
+        for row in canvas.frame.iter_mut() {
+            for pixel in row.iter_mut() {
+                *pixel = Color::decode(bytes)?;
+            }
+        }
+        Ok(canvas)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hdr_header(width: usize, height: usize) -> Vec<u8> {
+        format!(
+            "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+            height, width
+        )
+        .into_bytes()
+    }
+
+    #[test]
+    fn flat_hdr_is_decoded() {
+        let mut bytes = hdr_header(2, 1);
+        // 128 * 2^(129 - 136) = 1.0, 128 * 2^(131 - 136) = 4.0
+        bytes.extend([128, 0, 0, 129, 0, 128, 64, 131]);
+        let canvas = Canvas::from_hdr(&bytes).unwrap();
+        assert_eq!(canvas.color_at_pixel(0, 0), Color::RED());
+        assert_eq!(canvas.color_at_pixel(1, 0), Color::new(0.0, 4.0, 2.0));
+    }
+
+    #[test]
+    fn run_length_encoded_hdr_is_decoded() {
+        let mut bytes = hdr_header(8, 1);
+        bytes.extend([2, 2, 0, 8]);
+        // red: a run of 8, green: 8 literal values, blue: a run of 8, exponent: a run of 8
+        bytes.extend([128 + 8, 128]);
+        bytes.extend([8, 0, 16, 32, 48, 64, 80, 96, 112]);
+        bytes.extend([128 + 8, 0]);
+        bytes.extend([128 + 8, 129]);
+        let canvas = Canvas::from_hdr(&bytes).unwrap();
+        assert_eq!(canvas.width(), 8);
+        assert_eq!(canvas.color_at_pixel(0, 0), Color::RED());
+        assert_eq!(canvas.color_at_pixel(7, 0), Color::new(1.0, 0.875, 0.0));
+    }
+
+    #[test]
+    fn truncated_hdr_is_an_error() {
+        let mut bytes = hdr_header(2, 2);
+        bytes.extend([128, 0, 0, 129]);
+        assert!(Canvas::from_hdr(&bytes).is_err());
+    }
+
+    #[test]
+    fn empty_or_oversized_ppm_images_are_rejected_before_allocating() {
+        assert!(Canvas::from_ppm(b"P6 100000000 100000000 255\n\0\0\0").is_err());
+        assert!(Canvas::from_ppm(b"P3 100000000 100000000 255\n0 0 0").is_err());
+        assert!(Canvas::from_ppm(b"P3 0 4 255\n").is_err());
+        assert!(Canvas::from_ppm(b"P6 4 0 255\n").is_err());
+    }
+
+    #[test]
+    fn empty_or_oversized_hdr_images_are_rejected_before_allocating() {
+        let mut bytes = hdr_header(100_000, 100_000);
+        bytes.extend([2, 2, 0, 8]);
+        assert!(Canvas::from_hdr(&bytes).is_err());
+        assert!(Canvas::from_hdr(&hdr_header(0, 3)).is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use crate::consts::EPSILON;
+use crate::{consts::EPSILON, encoding::Encode};
 use std::{
     cmp::min,
     ops::{Add, Mul, Sub},
@@ -155,3 +155,14 @@ impl PartialEq for Color {
         true
     }
 }
+
+impl Encode for Color {
+    fn encode(&self, out: &mut Vec<u8>) {
+        [self.red, self.green, self.blue].encode(out);
+    }
+
+    fn decode(bytes: &mut &[u8]) -> std::io::Result<Self> {
+        let [red, green, blue] = <[f64; 3]>::decode(bytes)?;
+        Ok(Self::new(red, green, blue))
+    }
+}
@@ -1,23 +1,31 @@
-use crate::{body::Body, consts::EPSILON, tuple::Tuple};
-pub struct ComputedIntersection {
+use crate::{
+    body::{Body, Intersectable},
+    color::Color,
+    consts::{EPSILON, WAVELENGTH_BLUE, WAVELENGTH_GREEN, WAVELENGTH_RED},
+    material::cauchy_refractive_index,
+    tuple::Tuple,
+};
+pub struct ComputedIntersection<'a> {
     pub inside: bool,
     pub point: Tuple,
     pub over_point: Tuple,
     pub under_point: Tuple,
-    pub body: Body,
+    pub body: &'a Body,
     pub eyev: Tuple,
     pub normalv: Tuple,
     pub reflectv: Tuple,
     pub mu_from: f32,
     pub mu_to: f32,
+    pub abbe_from: f32,
+    pub abbe_to: f32,
 }
 
-impl ComputedIntersection {
+impl<'a> ComputedIntersection<'a> {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         inside: bool,
         point: Tuple,
-        body: Body,
+        body: &'a Body,
         eyev: Tuple,
         normalv: Tuple,
         reflectv: Tuple,
@@ -35,13 +43,73 @@ impl ComputedIntersection {
             reflectv,
             mu_from,
             mu_to,
+            abbe_from: 0.0,
+            abbe_to: 0.0,
         }
     }
 
+    pub fn with_abbe_numbers(mut self, abbe_from: f32, abbe_to: f32) -> Self {
+        self.abbe_from = abbe_from;
+        self.abbe_to = abbe_to;
+        self
+    }
+
+    /// Applies the bump or normal map of the body's material to the shading normal.
+    /// The over and under points keep following the geometric normal.
+    pub fn with_perturbed_normal(mut self) -> Self {
+        if let Some(perturbation) = self.body.material().normal_perturbation() {
+            // maps are defined against the outward normal
+            let outward = if self.inside {
+                -self.normalv
+            } else {
+                self.normalv
+            };
+            let perturbed = perturbation.perturb(self.body, self.point, outward);
+            self.normalv = if self.inside { -perturbed } else { perturbed };
+            self.reflectv = (-self.eyev).reflect(self.normalv);
+        }
+        self
+    }
+
+    pub fn is_dispersive(&self) -> bool {
+        self.abbe_from > 0.0 || self.abbe_to > 0.0
+    }
+
+    /// Refractive indices on either side of the surface for light of the given wavelength.
+    pub fn mu_at(&self, wavelength: f64) -> (f32, f32) {
+        (
+            cauchy_refractive_index(self.mu_from, self.abbe_from, wavelength),
+            cauchy_refractive_index(self.mu_to, self.abbe_to, wavelength),
+        )
+    }
+
     pub fn schlick(&self) -> f64 {
+        self.schlick_with_mu(self.mu_from, self.mu_to)
+    }
+
+    pub fn schlick_at(&self, wavelength: f64) -> f64 {
+        let (mu_from, mu_to) = self.mu_at(wavelength);
+        self.schlick_with_mu(mu_from, mu_to)
+    }
+
+    /// Reflectance of each color channel, which only differ for dispersive media.
+    pub fn schlick_color(&self) -> Color {
+        if self.is_dispersive() {
+            Color::new(
+                self.schlick_at(WAVELENGTH_RED),
+                self.schlick_at(WAVELENGTH_GREEN),
+                self.schlick_at(WAVELENGTH_BLUE),
+            )
+        } else {
+            let reflectance = self.schlick();
+            Color::new(reflectance, reflectance, reflectance)
+        }
+    }
+
+    fn schlick_with_mu(&self, mu_from: f32, mu_to: f32) -> f64 {
         let mut cos_i = self.eyev.dot(&self.normalv);
-        if self.mu_from > self.mu_to {
-            let n = self.mu_from / self.mu_to;
+        if mu_from > mu_to {
+            let n = mu_from / mu_to;
             let sin2_t = (n * n) as f64 * (1.0 - cos_i * cos_i);
             if sin2_t > 1.0 {
                 return 1.0;
@@ -49,7 +117,7 @@ impl ComputedIntersection {
             let cos_t = (1.0 - sin2_t).sqrt();
             cos_i = cos_t;
         }
-        let r0 = ((self.mu_from - self.mu_to) / (self.mu_from + self.mu_to)).powi(2) as f64;
+        let r0 = ((mu_from - mu_to) / (mu_from + mu_to)).powi(2) as f64;
         r0 + (1.0 - r0) * (1.0 - cos_i).powi(5)
     }
 }
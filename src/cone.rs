@@ -0,0 +1,268 @@
+use crate::{
+    body::{Body, Hits, Intersectable, IntoBody},
+    consts::EPSILON,
+    encoding::encode_fields,
+    material::{Material, Phong},
+    matrix::Matrix,
+    ray::Ray,
+    transformation::Transformation,
+    tuple::Tuple,
+};
+
+/// Single cone around the y axis between ```minimum``` and ```maximum```, with its radius
+/// growing linearly from ```bottom_radius``` to ```top_radius```. A zero radius at either
+/// end gives a plain cone, anything else a truncated one (frustum).
+#[derive(Clone, Debug)]
+pub struct Cone {
+    pub transform: Transformation,
+    pub material: Material,
+    pub minimum: f64,
+    pub maximum: f64,
+    pub bottom_radius: f64,
+    pub top_radius: f64,
+    pub is_closed: bool,
+}
+
+impl PartialEq for Cone {
+    fn eq(&self, other: &Self) -> bool {
+        self.transform == other.transform
+            && self.material == other.material
+            && self.minimum == other.minimum
+            && self.maximum == other.maximum
+            && self.bottom_radius == other.bottom_radius
+            && self.top_radius == other.top_radius
+            && self.is_closed == other.is_closed
+    }
+}
+
+impl Cone {
+    /// Closed cone with its unit radius base at y = 0 and its apex at y = 1.
+    pub fn new(transform: Matrix<4>, material: Material) -> Self {
+        Self {
+            transform: transform.into(),
+            material,
+            minimum: 0.0,
+            maximum: 1.0,
+            bottom_radius: 1.0,
+            top_radius: 0.0,
+            is_closed: true,
+        }
+    }
+
+    pub fn with_transform(mut self, t: Matrix<4>) -> Self {
+        self.transform.set(t);
+        self
+    }
+
+    pub fn with_material(mut self, m: Material) -> Self {
+        self.material = m;
+        self
+    }
+
+    pub fn with_extents(mut self, minimum: f64, maximum: f64) -> Self {
+        self.minimum = minimum;
+        self.maximum = maximum;
+        self
+    }
+
+    pub fn with_radii(mut self, bottom_radius: f64, top_radius: f64) -> Self {
+        self.bottom_radius = bottom_radius;
+        self.top_radius = top_radius;
+        self
+    }
+
+    pub fn with_caps(mut self, is_closed: bool) -> Self {
+        self.is_closed = is_closed;
+        self
+    }
+
+    /// Slope and offset of the radius as a function of y: ```radius = slope * y + offset```.
+    fn radius_line(&self) -> (f64, f64) {
+        let slope = (self.top_radius - self.bottom_radius) / (self.maximum - self.minimum);
+        (slope, self.bottom_radius - slope * self.minimum)
+    }
+
+    fn intersect_caps(&self, ray: &Ray) -> Hits {
+        if !self.is_closed || ray.direction.y.abs() < EPSILON {
+            return Hits::new();
+        }
+        let mut res = Hits::new();
+        for (y, radius) in [
+            (self.minimum, self.bottom_radius),
+            (self.maximum, self.top_radius),
+        ] {
+            if radius <= 0.0 {
+                continue;
+            }
+            let t = (y - ray.origin.y) / ray.direction.y;
+            let x = ray.origin.x + ray.direction.x * t;
+            let z = ray.origin.z + ray.direction.z * t;
+            if x.powi(2) + z.powi(2) <= radius.powi(2) {
+                res.push(t);
+            }
+        }
+        res
+    }
+}
+
+impl Default for Cone {
+    fn default() -> Self {
+        Self::new(Matrix::Identity(), Material::Phong(Phong::default()))
+    }
+}
+
+impl Intersectable for Cone {
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn transformation(&self) -> &Transformation {
+        &self.transform
+    }
+
+    fn transform_mut(&mut self) -> &mut Transformation {
+        &mut self.transform
+    }
+
+    fn intersect_in_object_space(&self, object_space_ray: &Ray) -> Hits {
+        let (o, d) = (object_space_ray.origin, object_space_ray.direction);
+        let (slope, offset) = self.radius_line();
+        // x² + z² = (slope * y + offset)²
+        let radius_at_origin = slope * o.y + offset;
+        let a = d.x.powi(2) + d.z.powi(2) - (slope * d.y).powi(2);
+        let b = 2.0 * (o.x * d.x + o.z * d.z - slope * d.y * radius_at_origin);
+        let c = o.x.powi(2) + o.z.powi(2) - radius_at_origin.powi(2);
+
+        let candidates = if a.abs() < EPSILON {
+            if b.abs() < EPSILON {
+                Hits::new()
+            } else {
+                Hits::from([-c / b])
+            }
+        } else {
+            let disc = b.powi(2) - 4.0 * a * c;
+            if disc < 0.0 {
+                Hits::new()
+            } else {
+                Hits::from([
+                    (-b - disc.sqrt()) / (2.0 * a),
+                    (-b + disc.sqrt()) / (2.0 * a),
+                ])
+            }
+        };
+
+        let mut res: Hits = candidates
+            .into_iter()
+            .filter(|t| {
+                let y = o.y + t * d.y;
+                // the other nappe has a negative radius
+                y > self.minimum && y < self.maximum && slope * y + offset >= 0.0
+            })
+            .collect();
+        res.extend(self.intersect_caps(object_space_ray));
+        res.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        res
+    }
+
+    fn normal_at_in_object_space(&self, object_space_point: Tuple) -> Tuple {
+        let dist = object_space_point.x.powi(2) + object_space_point.z.powi(2);
+        let y = object_space_point.y;
+        if self.is_closed && dist < self.bottom_radius.powi(2) && y <= self.minimum + EPSILON {
+            Tuple::Vector(0.0, -1.0, 0.0)
+        } else if self.is_closed && dist < self.top_radius.powi(2) && y >= self.maximum - EPSILON {
+            Tuple::Vector(0.0, 1.0, 0.0)
+        } else {
+            let (slope, offset) = self.radius_line();
+            Tuple::Vector(
+                object_space_point.x,
+                -slope * (slope * y + offset),
+                object_space_point.z,
+            )
+            .normalize()
+        }
+    }
+}
+
+impl From<Cone> for Body {
+    fn from(c: Cone) -> Self {
+        Body::Cone(c)
+    }
+}
+
+impl IntoBody for Cone {
+    fn into_body(&self) -> Body {
+        Body::Cone(self.clone())
+    }
+}
+
+encode_fields!(Cone {
+    transform,
+    material,
+    minimum,
+    maximum,
+    bottom_radius,
+    top_radius,
+    is_closed,
+});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consts::SQRT_2;
+
+    fn assert_distances(actual: Hits, expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len(), "distances were {actual:?}");
+        for (t, expected) in actual.iter().zip(expected) {
+            assert!((t - expected).abs() < 1e-9, "distances were {actual:?}");
+        }
+    }
+
+    #[test]
+    fn only_one_nappe_is_hit() {
+        // apex at y = 1, so the mirrored nappe above it must not show up
+        let cone = Cone::default()
+            .with_caps(false)
+            .with_extents(-10.0, 1.0)
+            .with_radii(11.0, 0.0);
+        let ray = Ray::new(Tuple::Point(-5.0, 0.5, 0.0), Tuple::Vector(1.0, 0.0, 0.0));
+        assert_distances(cone.intersect_in_object_space(&ray), &[4.5, 5.5]);
+        let ray = Ray::new(Tuple::Point(-5.0, 1.5, 0.0), Tuple::Vector(1.0, 0.0, 0.0));
+        assert_distances(cone.intersect_in_object_space(&ray), &[]);
+    }
+
+    #[test]
+    fn frustum_is_hit_on_its_sides_and_caps() {
+        let frustum = Cone::default().with_extents(-1.0, 1.0).with_radii(2.0, 1.0);
+        // radius is 1.5 at y = 0
+        let ray = Ray::new(Tuple::Point(-5.0, 0.0, 0.0), Tuple::Vector(1.0, 0.0, 0.0));
+        assert_distances(frustum.intersect_in_object_space(&ray), &[3.5, 6.5]);
+        // straight down the middle through both caps
+        let ray = Ray::new(Tuple::Point(0.0, 5.0, 0.0), Tuple::Vector(0.0, -1.0, 0.0));
+        assert_distances(frustum.intersect_in_object_space(&ray), &[4.0, 6.0]);
+        // down through the wide bottom cap only after entering through the side
+        let ray = Ray::new(Tuple::Point(1.75, 5.0, 0.0), Tuple::Vector(0.0, -1.0, 0.0));
+        assert_distances(frustum.intersect_in_object_space(&ray), &[5.5, 6.0]);
+    }
+
+    #[test]
+    fn normals_of_sides_and_caps() {
+        let cone = Cone::default();
+        assert_eq!(
+            cone.normal_at_in_object_space(Tuple::Point(0.5, 0.5, 0.0)),
+            Tuple::Vector(1.0 / SQRT_2, 1.0 / SQRT_2, 0.0)
+        );
+        assert_eq!(
+            cone.normal_at_in_object_space(Tuple::Point(0.3, 0.0, 0.2)),
+            Tuple::Vector(0.0, -1.0, 0.0)
+        );
+        let frustum = Cone::default().with_radii(1.0, 0.5);
+        assert_eq!(
+            frustum.normal_at_in_object_space(Tuple::Point(0.1, 1.0, 0.1)),
+            Tuple::Vector(0.0, 1.0, 0.0)
+        );
+    }
+}
@@ -1,16 +1,18 @@
 use crate::{
-    body::{Body, Intersectable, IntoBody},
+    body::{Body, Hits, Intersectable, IntoBody},
     consts::EPSILON,
+    encoding::encode_fields,
     material::Material,
     matrix::Matrix,
     max, min,
     ray::Ray,
+    transformation::Transformation,
     tuple::Tuple,
 };
 
 #[derive(Clone, Debug)]
 pub struct Cube {
-    transform: Matrix<4>,
+    transform: Transformation,
     material: Material,
 }
 
@@ -23,13 +25,13 @@ impl PartialEq for Cube {
 impl Cube {
     pub fn new(transform: Matrix<4>, material: Material) -> Self {
         Self {
-            transform,
+            transform: transform.into(),
             material,
         }
     }
 
     pub fn with_transform(mut self, t: Matrix<4>) -> Self {
-        self.transform = t;
+        self.transform.set(t);
         self
     }
 
@@ -48,15 +50,15 @@ impl Intersectable for Cube {
         &mut self.material
     }
 
-    fn transform(&self) -> Matrix<4> {
-        self.transform
+    fn transformation(&self) -> &Transformation {
+        &self.transform
     }
 
-    fn transform_mut(&mut self) -> &mut Matrix<4_usize> {
+    fn transform_mut(&mut self) -> &mut Transformation {
         &mut self.transform
     }
 
-    fn intersect_in_object_space(&self, object_space_ray: &Ray) -> Vec<f64> {
+    fn intersect_in_object_space(&self, object_space_ray: &Ray) -> Hits {
         fn check_axis(origin: f64, direction: f64) -> (f64, f64) {
             let tmin_numerator = -1.0 - origin;
             let tmax_numerator = 1.0 - origin;
@@ -83,9 +85,9 @@ impl Intersectable for Cube {
         let tmax = min!(xtmax, ytmax, ztmax);
 
         if tmin > tmax {
-            vec![]
+            Hits::new()
         } else {
-            vec![tmin, tmax]
+            Hits::from([tmin, tmax])
         }
     }
 
@@ -124,3 +126,8 @@ impl IntoBody for Cube {
         Body::Cube(self.clone())
     }
 }
+
+encode_fields!(Cube {
+    transform,
+    material
+});
@@ -1,38 +1,75 @@
 use crate::{
-    body::{Body, Intersectable, IntoBody},
+    body::{Body, Hits, Intersectable, IntoBody},
     consts::EPSILON,
+    encoding::encode_fields,
     material::Material,
     matrix::Matrix,
     ray::Ray,
+    transformation::Transformation,
     tuple::Tuple,
 };
 
+/// Unit radius cylinder around the y axis between ```minimum``` and ```maximum```,
+/// each end optionally closed by a cap.
 #[derive(Clone, Debug)]
 pub struct Cylinder {
-    pub transform: Matrix<4>,
+    pub transform: Transformation,
     pub material: Material,
-    pub height: f64,
-    pub is_closed: bool,
+    pub minimum: f64,
+    pub maximum: f64,
+    pub bottom_closed: bool,
+    pub top_closed: bool,
 }
 
 impl PartialEq for Cylinder {
     fn eq(&self, other: &Self) -> bool {
         self.transform == other.transform
             && self.material == other.material
-            && self.height == other.height
-            && self.is_closed == other.is_closed
+            && self.minimum == other.minimum
+            && self.maximum == other.maximum
+            && self.bottom_closed == other.bottom_closed
+            && self.top_closed == other.top_closed
     }
 }
 
 impl Cylinder {
+    /// Cylinder of the given height centred on the origin, with both ends closed or open.
     pub fn new(transform: Matrix<4>, material: Material, height: f64, is_closed: bool) -> Self {
         Self {
-            transform,
+            transform: transform.into(),
             material,
-            height,
-            is_closed,
+            minimum: -height / 2.0,
+            maximum: height / 2.0,
+            bottom_closed: is_closed,
+            top_closed: is_closed,
         }
     }
+
+    /// Open cylinder extending forever in both directions.
+    pub fn infinite(transform: Matrix<4>, material: Material) -> Self {
+        Self::new(transform, material, 0.0, false).with_extents(f64::NEG_INFINITY, f64::INFINITY)
+    }
+
+    pub fn with_extents(mut self, minimum: f64, maximum: f64) -> Self {
+        self.minimum = minimum;
+        self.maximum = maximum;
+        self
+    }
+
+    pub fn with_caps(mut self, bottom_closed: bool, top_closed: bool) -> Self {
+        self.bottom_closed = bottom_closed;
+        self.top_closed = top_closed;
+        self
+    }
+
+    pub fn height(&self) -> f64 {
+        self.maximum - self.minimum
+    }
+
+    /// Whether both ends are capped.
+    pub fn is_closed(&self) -> bool {
+        self.bottom_closed && self.top_closed
+    }
 }
 
 impl Intersectable for Cylinder {
@@ -44,35 +81,36 @@ impl Intersectable for Cylinder {
         &mut self.material
     }
 
-    fn transform(&self) -> Matrix<4_usize> {
-        self.transform
+    fn transformation(&self) -> &Transformation {
+        &self.transform
     }
 
-    fn transform_mut(&mut self) -> &mut Matrix<4_usize> {
+    fn transform_mut(&mut self) -> &mut Transformation {
         &mut self.transform
     }
 
-    fn intersect_in_object_space(&self, object_space_ray: &Ray) -> Vec<f64> {
+    fn intersect_in_object_space(&self, object_space_ray: &Ray) -> Hits {
         fn check_cap(ray: &Ray, t: f64) -> bool {
             let x = ray.origin.x + ray.direction.x * t;
             let z = ray.origin.z + ray.direction.z * t;
             x.powi(2) + z.powi(2) <= 1.0
         }
-        fn intersect_caps(cyl: &Cylinder, ray: &Ray) -> Vec<f64> {
-            if !cyl.is_closed || ray.direction.y.abs() < EPSILON {
-                return vec![];
+        fn intersect_caps(cyl: &Cylinder, ray: &Ray) -> Hits {
+            if ray.direction.y.abs() < EPSILON {
+                return Hits::new();
             }
-            let min = -cyl.height / 2.0;
-            let max = cyl.height / 2.0;
-
-            let tmin = (min - ray.origin.y) / ray.direction.y;
-            let tmax = (max - ray.origin.y) / ray.direction.y;
-            let mut res = vec![];
-            if check_cap(ray, tmin) {
-                res.push(tmin);
-            }
-            if check_cap(ray, tmax) {
-                res.push(tmax);
+            let mut res = Hits::new();
+            for (closed, y) in [
+                (cyl.bottom_closed, cyl.minimum),
+                (cyl.top_closed, cyl.maximum),
+            ] {
+                if !closed || !y.is_finite() {
+                    continue;
+                }
+                let t = (y - ray.origin.y) / ray.direction.y;
+                if check_cap(ray, t) {
+                    res.push(t);
+                }
             }
             res
         }
@@ -90,15 +128,16 @@ impl Intersectable for Cylinder {
             std::mem::swap(&mut d1, &mut d2);
         }
 
-        let mut res = vec![];
+        let (minimum, maximum) = (self.minimum, self.maximum);
+        let mut res = Hits::new();
 
         let y1 = object_space_ray.origin.y + d1 * object_space_ray.direction.y;
-        if y1 > -self.height / 2.0 && y1 < self.height / 2.0 && !d1.is_nan() {
+        if y1 > minimum && y1 < maximum && !d1.is_nan() {
             res.push(d1);
         }
 
         let y2 = object_space_ray.origin.y + d2 * object_space_ray.direction.y;
-        if y2 > -self.height / 2.0 && y2 < self.height / 2.0 && !d2.is_nan() {
+        if y2 > minimum && y2 < maximum && !d2.is_nan() {
             res.push(d2);
         }
 
@@ -113,9 +152,9 @@ impl Intersectable for Cylinder {
     fn normal_at_in_object_space(&self, object_space_point: Tuple) -> Tuple {
         let dist = object_space_point.x.powi(2) + object_space_point.z.powi(2);
         let y = object_space_point.y;
-        if dist < 1.0 && y <= -self.height / 2.0 + EPSILON {
+        if self.bottom_closed && dist < 1.0 && y <= self.minimum + EPSILON {
             Tuple::Vector(0.0, -1.0, 0.0)
-        } else if dist < 1.0 && y >= self.height / 2.0 - EPSILON {
+        } else if self.top_closed && dist < 1.0 && y >= self.maximum - EPSILON {
             Tuple::Vector(0.0, 1.0, 0.0)
         } else {
             Tuple::Vector(object_space_point.x, 0.0, object_space_point.z)
@@ -141,6 +180,15 @@ impl IntoBody for Cylinder {
     }
 }
 
+encode_fields!(Cylinder {
+    transform,
+    material,
+    minimum,
+    maximum,
+    bottom_closed,
+    top_closed,
+});
+
 #[cfg(test)]
 mod tests {
     use crate::material::Phong;
@@ -158,8 +206,56 @@ mod tests {
         );
         let ray = Ray::new(Tuple::Point(-1.0, -2.0, 0.0), Tuple::Vector(1.0, 1.0, 0.0));
         dbg!(cyl.normal_at_in_object_space(Tuple::Point(0.0, -2.0, 0.0)));
+        let cyl = Body::from(cyl);
         let res = cyl.intersect(&ray);
 
         dbg!(res.hit());
     }
+
+    #[test]
+    fn new_centres_the_cylinder_on_the_origin() {
+        let cyl = Cylinder::new(
+            Matrix::Identity(),
+            Material::Phong(Phong::default()),
+            2.0,
+            true,
+        );
+        assert_eq!((cyl.minimum, cyl.maximum), (-1.0, 1.0));
+        assert!(cyl.bottom_closed && cyl.top_closed);
+        assert_eq!((cyl.height(), cyl.is_closed()), (2.0, true));
+    }
+
+    #[test]
+    fn extents_and_caps_are_independent() {
+        // standing on y = 0 with only its top closed, like an upturned cup
+        let cyl = Cylinder::new(
+            Matrix::Identity(),
+            Material::Phong(Phong::default()),
+            2.0,
+            true,
+        )
+        .with_extents(0.0, 3.0)
+        .with_caps(false, true);
+        assert_eq!((cyl.height(), cyl.is_closed()), (3.0, false));
+        let down = Tuple::Vector(0.0, -1.0, 0.0);
+        let ray = Ray::new(Tuple::Point(0.5, 5.0, 0.0), down);
+        assert_eq!(cyl.intersect_in_object_space(&ray), vec![2.0]);
+        let ray = Ray::new(Tuple::Point(-5.0, 2.5, 0.0), Tuple::Vector(1.0, 0.0, 0.0));
+        assert_eq!(cyl.intersect_in_object_space(&ray), vec![4.0, 6.0]);
+        let ray = Ray::new(Tuple::Point(-5.0, -0.5, 0.0), Tuple::Vector(1.0, 0.0, 0.0));
+        assert!(cyl.intersect_in_object_space(&ray).is_empty());
+        assert_eq!(
+            cyl.normal_at_in_object_space(Tuple::Point(0.5, 3.0, 0.0)),
+            Tuple::Vector(0.0, 1.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn infinite_cylinder_has_no_ends() {
+        let cyl = Cylinder::infinite(Matrix::Identity(), Material::Phong(Phong::default()));
+        let ray = Ray::new(Tuple::Point(-5.0, 1e6, 0.0), Tuple::Vector(1.0, 0.0, 0.0));
+        assert_eq!(cyl.intersect_in_object_space(&ray), vec![4.0, 6.0]);
+        let ray = Ray::new(Tuple::Point(0.0, 5.0, 0.0), Tuple::Vector(0.0, -1.0, 0.0));
+        assert!(cyl.intersect_in_object_space(&ray).is_empty());
+    }
 }
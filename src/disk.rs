@@ -0,0 +1,157 @@
+use crate::{
+    body::{Body, Hits, Intersectable, IntoBody},
+    consts::EPSILON,
+    encoding::encode_fields,
+    material::{Material, Phong},
+    matrix::Matrix,
+    ray::Ray,
+    transformation::Transformation,
+    tuple::Tuple,
+};
+
+/// Flat disk in the xz plane facing +y. A non-zero ```inner_radius``` cuts a hole
+/// in the middle, making it an annulus.
+#[derive(Clone, Debug)]
+pub struct Disk {
+    pub transform: Transformation,
+    pub material: Material,
+    pub inner_radius: f64,
+    pub outer_radius: f64,
+}
+
+impl PartialEq for Disk {
+    fn eq(&self, other: &Self) -> bool {
+        self.transform == other.transform
+            && self.material == other.material
+            && self.inner_radius == other.inner_radius
+            && self.outer_radius == other.outer_radius
+    }
+}
+
+impl Disk {
+    pub fn new(
+        transform: Matrix<4>,
+        material: Material,
+        inner_radius: f64,
+        outer_radius: f64,
+    ) -> Self {
+        Self {
+            transform: transform.into(),
+            material,
+            inner_radius,
+            outer_radius,
+        }
+    }
+
+    pub fn with_transform(mut self, t: Matrix<4>) -> Self {
+        self.transform.set(t);
+        self
+    }
+
+    pub fn with_material(mut self, m: Material) -> Self {
+        self.material = m;
+        self
+    }
+}
+
+impl Default for Disk {
+    fn default() -> Self {
+        Self::new(
+            Matrix::Identity(),
+            Material::Phong(Phong::default()),
+            0.0,
+            1.0,
+        )
+    }
+}
+
+impl Intersectable for Disk {
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn transformation(&self) -> &Transformation {
+        &self.transform
+    }
+
+    fn transform_mut(&mut self) -> &mut Transformation {
+        &mut self.transform
+    }
+
+    fn intersect_in_object_space(&self, object_space_ray: &Ray) -> Hits {
+        if object_space_ray.direction.y.abs() < EPSILON {
+            return Hits::new();
+        }
+        let t = -object_space_ray.origin.y / object_space_ray.direction.y;
+        let point = object_space_ray.position(t);
+        let dist = point.x.powi(2) + point.z.powi(2);
+        if dist >= self.inner_radius.powi(2) && dist <= self.outer_radius.powi(2) {
+            Hits::from([t])
+        } else {
+            Hits::new()
+        }
+    }
+
+    fn normal_at_in_object_space(&self, _object_space_point: Tuple) -> Tuple {
+        Tuple::Vector(0.0, 1.0, 0.0)
+    }
+}
+
+impl From<Disk> for Body {
+    fn from(d: Disk) -> Self {
+        Body::Disk(d)
+    }
+}
+
+impl IntoBody for Disk {
+    fn into_body(&self) -> Body {
+        Body::Disk(self.clone())
+    }
+}
+
+encode_fields!(Disk {
+    transform,
+    material,
+    inner_radius,
+    outer_radius
+});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn annulus_is_hit_between_its_radii() {
+        let annulus = Disk::new(
+            Matrix::Identity(),
+            Material::Phong(Phong::default()),
+            0.5,
+            1.0,
+        );
+        let down = Tuple::Vector(0.0, -1.0, 0.0);
+        for (x, expected) in [(0.0, vec![]), (0.75, vec![2.0]), (1.5, vec![])] {
+            let ray = Ray::new(Tuple::Point(x, 2.0, 0.0), down);
+            assert_eq!(annulus.intersect_in_object_space(&ray), expected);
+        }
+        let parallel = Ray::new(Tuple::Point(0.75, 0.0, -5.0), Tuple::Vector(0.0, 0.0, 1.0));
+        assert!(annulus.intersect_in_object_space(&parallel).is_empty());
+    }
+
+    #[test]
+    fn transformed_disk_faces_its_transform() {
+        let disk = Disk::default().with_transform(Matrix::rotation_X(crate::consts::PI_BY_2));
+        assert_eq!(
+            disk.normal_at(Tuple::Point(0.0, 0.0, 0.0)),
+            Tuple::Vector(0.0, 0.0, 1.0)
+        );
+        let ray = Ray::new(Tuple::Point(0.2, 0.3, -4.0), Tuple::Vector(0.0, 0.0, 1.0));
+        let disk = Body::from(disk);
+        let xs = disk.intersect(&ray);
+        assert_eq!(xs.count(), 1);
+        assert_eq!(xs[0].t, 4.0);
+    }
+}
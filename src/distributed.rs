@@ -0,0 +1,386 @@
+use crate::{
+    camera::{Camera, Region},
+    canvas::{Canvas, Sized},
+    encoding::{invalid, Encode},
+    world::World,
+};
+use std::{
+    collections::VecDeque,
+    io::{ErrorKind, Read, Result, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Condvar, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+// Every frame is a one byte kind, the payload length as a little endian u32, then the payload.
+const SCENE: u8 = 0; // coordinator -> worker: encoded (Camera, World)
+const TILE: u8 = 1; // coordinator -> worker: encoded Region
+const PIXELS: u8 = 2; // worker -> coordinator: encoded (Region, Canvas)
+const DONE: u8 = 3; // coordinator -> worker: no more tiles, empty payload
+
+const MAX_FRAME_LEN: usize = 1 << 30;
+
+// How often the coordinator checks for new workers while waiting for tiles.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+// How long a worker may take to answer before its tile goes to someone else, and how long
+// the coordinator waits without any worker before giving up.
+const WORKER_TIMEOUT: Duration = Duration::from_secs(60);
+
+fn write_frame(stream: &mut impl Write, kind: u8, payload: &[u8]) -> Result<()> {
+    let len = u32::try_from(payload.len()).map_err(|_| invalid("frame is too large"))?;
+    let mut frame = Vec::with_capacity(5 + payload.len());
+    frame.push(kind);
+    frame.extend_from_slice(&len.to_le_bytes());
+    frame.extend_from_slice(payload);
+    stream.write_all(&frame)
+}
+
+fn read_frame(stream: &mut impl Read) -> Result<(u8, Vec<u8>)> {
+    let mut header = [0; 5];
+    stream.read_exact(&mut header)?;
+    let len = u32::from_le_bytes(header[1..].try_into().unwrap()) as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(invalid("frame is too large"));
+    }
+    let mut payload = vec![0; len];
+    stream.read_exact(&mut payload)?;
+    Ok((header[0], payload))
+}
+
+fn unexpected_frame(kind: u8) -> std::io::Error {
+    invalid(&format!("unexpected frame kind {kind}"))
+}
+
+/// Splits the image into ```camera.tile_size``` squares, row by row.
+fn tiles(camera: &Camera) -> Vec<Region> {
+    let size = camera.tile_size.max(1);
+    (0..camera.vsize)
+        .step_by(size)
+        .flat_map(|y| {
+            (0..camera.hsize).step_by(size).map(move |x| {
+                Region::new(x, y, size.min(camera.hsize - x), size.min(camera.vsize - y))
+            })
+        })
+        .collect()
+}
+
+/// Tiles waiting to be rendered, shared by the threads serving each worker.
+struct TileQueue {
+    // pending tiles and the number of tiles not yet rendered
+    state: Mutex<(VecDeque<Region>, usize)>,
+    changed: Condvar,
+}
+
+impl TileQueue {
+    fn new(tiles: Vec<Region>) -> Self {
+        let unfinished = tiles.len();
+        Self {
+            state: Mutex::new((tiles.into(), unfinished)),
+            changed: Condvar::new(),
+        }
+    }
+
+    /// Next tile to render, waiting while others are still out with workers that may die.
+    /// ```None``` once every tile is done.
+    fn next(&self) -> Option<Region> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if state.1 == 0 {
+                return None;
+            }
+            if let Some(region) = state.0.pop_front() {
+                return Some(region);
+            }
+            state = self.changed.wait(state).unwrap();
+        }
+    }
+
+    fn give_back(&self, region: Region) {
+        self.state.lock().unwrap().0.push_back(region);
+        self.changed.notify_one();
+    }
+
+    fn finish(&self) {
+        let mut state = self.state.lock().unwrap();
+        // a closed queue has already given up on its unfinished tiles
+        state.1 = state.1.saturating_sub(1);
+        if state.1 == 0 {
+            self.changed.notify_all();
+        }
+    }
+
+    /// Stops handing out tiles, e.g. when the coordinator gives up.
+    fn close(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.0.clear();
+        state.1 = 0;
+        self.changed.notify_all();
+    }
+}
+
+/// Renders the image by handing out tiles to workers that connect to ```listener```, see
+/// ```work```. Workers may join at any time. A worker whose connection fails, who sends
+/// back garbage or who takes longer than a minute to answer gets no more tiles, and the
+/// tile it held goes to the next free worker. Fails once no worker has been connected for
+/// a minute, e.g. because every worker died.
+pub fn render_distributed(
+    camera: &Camera,
+    world: &World,
+    listener: &TcpListener,
+) -> Result<Canvas> {
+    render_with_timeout(camera, world, listener, WORKER_TIMEOUT)
+}
+
+fn render_with_timeout(
+    camera: &Camera,
+    world: &World,
+    listener: &TcpListener,
+    timeout: Duration,
+) -> Result<Canvas> {
+    let mut scene = camera.to_bytes();
+    world.encode(&mut scene);
+    let tiles = tiles(camera);
+    let mut remaining = tiles.len();
+    let queue = TileQueue::new(tiles);
+    let (sender, results) = mpsc::channel();
+    let connected = AtomicUsize::new(0);
+
+    listener.set_nonblocking(true)?;
+    let canvas = thread::scope(|scope| {
+        let mut canvas = Canvas::new(camera.hsize, camera.vsize);
+        let mut unattended_since = Instant::now();
+        while remaining > 0 {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    connected.fetch_add(1, Ordering::SeqCst);
+                    let (scene, queue, sender) = (&scene, &queue, sender.clone());
+                    let connected = &connected;
+                    scope.spawn(move || {
+                        serve(stream, scene, queue, sender, timeout);
+                        connected.fetch_sub(1, Ordering::SeqCst);
+                    });
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => {}
+                Err(err) => {
+                    queue.close();
+                    return Err(err);
+                }
+            }
+            if connected.load(Ordering::SeqCst) > 0 {
+                unattended_since = Instant::now();
+            } else if unattended_since.elapsed() > timeout {
+                queue.close();
+                return Err(std::io::Error::new(
+                    ErrorKind::TimedOut,
+                    "no render worker is left to finish the image",
+                ));
+            }
+            if let Ok((region, pixels)) = results.recv_timeout(POLL_INTERVAL) {
+                canvas.paste(region.x, region.y, &pixels);
+                remaining -= 1;
+            }
+        }
+        // workers that connected too late to get a tile are sent home straight away
+        while let Ok((mut stream, _)) = listener.accept() {
+            stream.set_nonblocking(false)?;
+            write_frame(&mut stream, DONE, &[]).ok();
+        }
+        Ok(canvas)
+    });
+    listener.set_nonblocking(false)?;
+    canvas
+}
+
+/// Feeds tiles to one worker until there are none left or the worker fails.
+fn serve(
+    mut stream: TcpStream,
+    scene: &[u8],
+    queue: &TileQueue,
+    results: mpsc::Sender<(Region, Canvas)>,
+    timeout: Duration,
+) {
+    let mut serve_tiles = || -> Result<()> {
+        stream.set_nonblocking(false)?;
+        // a worker that hangs without closing its connection is treated as dead
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+        write_frame(&mut stream, SCENE, scene)?;
+        while let Some(region) = queue.next() {
+            match request_tile(&mut stream, region) {
+                Ok(pixels) => {
+                    queue.finish();
+                    results.send((region, pixels)).ok();
+                }
+                Err(err) => {
+                    queue.give_back(region);
+                    return Err(err);
+                }
+            }
+        }
+        write_frame(&mut stream, DONE, &[])
+    };
+    // a failed worker is simply dropped, its tile is already back in the queue
+    serve_tiles().ok();
+}
+
+fn request_tile(stream: &mut TcpStream, region: Region) -> Result<Canvas> {
+    write_frame(stream, TILE, &region.to_bytes())?;
+    match read_frame(stream)? {
+        (PIXELS, payload) => {
+            let (rendered, pixels) = <(Region, Canvas)>::from_bytes(&payload)?;
+            if rendered != region
+                || (pixels.width(), pixels.height()) != (region.width, region.height)
+            {
+                return Err(invalid("worker sent pixels for the wrong tile"));
+            }
+            Ok(pixels)
+        }
+        (kind, _) => Err(unexpected_frame(kind)),
+    }
+}
+
+/// Connects to a coordinator running ```render_distributed``` and renders the tiles it
+/// hands out until it says the image is done.
+pub fn work(coordinator: impl ToSocketAddrs) -> Result<()> {
+    work_on(TcpStream::connect(coordinator)?)
+}
+
+fn work_on(mut stream: TcpStream) -> Result<()> {
+    let (camera, world) = match read_frame(&mut stream)? {
+        (SCENE, payload) => <(Camera, World)>::from_bytes(&payload)?,
+        (DONE, _) => return Ok(()),
+        (kind, _) => return Err(unexpected_frame(kind)),
+    };
+    loop {
+        match read_frame(&mut stream)? {
+            (TILE, payload) => {
+                let region = Region::from_bytes(&payload)?;
+                let pixels = camera.render_region(&world, region);
+                write_frame(&mut stream, PIXELS, &(region, pixels).to_bytes())?;
+            }
+            (DONE, _) => return Ok(()),
+            (kind, _) => return Err(unexpected_frame(kind)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tuple::Tuple;
+
+    fn scene() -> (Camera, World) {
+        let camera = Camera::new(23, 17, crate::consts::PI_BY_2)
+            .look_at_from_position(
+                Tuple::Point(0, 0, -5),
+                Tuple::Point(0, 0, 0),
+                Tuple::Vector(0, 1, 0),
+            )
+            .with_tile_size(5);
+        (camera, World::default_from_book())
+    }
+
+    #[test]
+    fn workers_render_the_same_image_as_one_process() {
+        let (camera, world) = scene();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let workers: Vec<_> = (0..3)
+            .map(|_| {
+                let stream = TcpStream::connect(address).unwrap();
+                thread::spawn(move || work_on(stream))
+            })
+            .collect();
+        let image = render_distributed(&camera, &world, &listener).unwrap();
+        for worker in workers {
+            worker.join().unwrap().unwrap();
+        }
+        assert_eq!(image, camera.render(&world));
+    }
+
+    #[test]
+    fn tiles_of_dead_workers_are_reassigned() {
+        let (camera, world) = scene();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let (took_tile, tile_taken) = mpsc::channel();
+        // takes a tile and disconnects without answering
+        let dying = thread::spawn(move || {
+            let mut stream = TcpStream::connect(address).unwrap();
+            assert_eq!(read_frame(&mut stream).unwrap().0, SCENE);
+            assert_eq!(read_frame(&mut stream).unwrap().0, TILE);
+            took_tile.send(()).unwrap();
+        });
+        let healthy = thread::spawn(move || {
+            tile_taken.recv().unwrap();
+            work(address)
+        });
+        let image = render_distributed(&camera, &world, &listener).unwrap();
+        dying.join().unwrap();
+        healthy.join().unwrap().unwrap();
+        assert_eq!(image, camera.render(&world));
+    }
+
+    #[test]
+    fn tiles_of_hung_workers_are_reassigned() {
+        let (camera, world) = scene();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let (took_tile, tile_taken) = mpsc::channel();
+        let (render_done, wait_for_render) = mpsc::channel::<()>();
+        // takes a tile and never answers, keeping its connection open until the end
+        let hung = thread::spawn(move || {
+            let mut stream = TcpStream::connect(address).unwrap();
+            assert_eq!(read_frame(&mut stream).unwrap().0, SCENE);
+            assert_eq!(read_frame(&mut stream).unwrap().0, TILE);
+            took_tile.send(()).unwrap();
+            wait_for_render.recv().ok();
+        });
+        let healthy = thread::spawn(move || {
+            tile_taken.recv().unwrap();
+            work(address)
+        });
+        let image =
+            render_with_timeout(&camera, &world, &listener, Duration::from_millis(200)).unwrap();
+        render_done.send(()).unwrap();
+        hung.join().unwrap();
+        healthy.join().unwrap().unwrap();
+        assert_eq!(image, camera.render(&world));
+    }
+
+    #[test]
+    fn render_fails_once_every_worker_died() {
+        let (camera, world) = scene();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let dying = thread::spawn(move || {
+            let mut stream = TcpStream::connect(address).unwrap();
+            assert_eq!(read_frame(&mut stream).unwrap().0, SCENE);
+            assert_eq!(read_frame(&mut stream).unwrap().0, TILE);
+        });
+        let err = render_with_timeout(&camera, &world, &listener, Duration::from_millis(200))
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+        dying.join().unwrap();
+    }
+
+    #[test]
+    fn frames_round_trip_and_reject_oversized_lengths() {
+        let mut wire = vec![];
+        write_frame(&mut wire, TILE, &Region::new(1, 2, 3, 4).to_bytes()).unwrap();
+        let (kind, payload) = read_frame(&mut wire.as_slice()).unwrap();
+        assert_eq!(kind, TILE);
+        assert_eq!(
+            Region::from_bytes(&payload).unwrap(),
+            Region::new(1, 2, 3, 4)
+        );
+
+        let oversized = [PIXELS, 0xff, 0xff, 0xff, 0xff];
+        assert!(read_frame(&mut oversized.as_slice()).is_err());
+    }
+}
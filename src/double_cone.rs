@@ -1,15 +1,17 @@
 use crate::{
-    body::{Body, Intersectable, IntoBody},
+    body::{Body, Hits, Intersectable, IntoBody},
     consts::EPSILON,
+    encoding::encode_fields,
     material::Material,
     matrix::Matrix,
     ray::Ray,
+    transformation::Transformation,
     tuple::Tuple,
 };
 
 #[derive(Clone, Debug)]
 pub struct DoubleCone {
-    transform: Matrix<4>,
+    transform: Transformation,
     material: Material,
     height: f64,
     is_closed: bool,
@@ -27,7 +29,7 @@ impl PartialEq for DoubleCone {
 impl DoubleCone {
     pub fn new(transform: Matrix<4>, material: Material, height: f64, is_closed: bool) -> Self {
         Self {
-            transform,
+            transform: transform.into(),
             material,
             height,
             is_closed,
@@ -44,31 +46,31 @@ impl Intersectable for DoubleCone {
         &mut self.material
     }
 
-    fn transform(&self) -> Matrix<4_usize> {
-        self.transform
+    fn transformation(&self) -> &Transformation {
+        &self.transform
     }
 
-    fn transform_mut(&mut self) -> &mut Matrix<4_usize> {
+    fn transform_mut(&mut self) -> &mut Transformation {
         &mut self.transform
     }
 
-    fn intersect_in_object_space(&self, object_space_ray: &Ray) -> Vec<f64> {
+    fn intersect_in_object_space(&self, object_space_ray: &Ray) -> Hits {
         fn check_cap(ray: &Ray, t: f64) -> bool {
             let x = ray.origin.x + ray.direction.x * t;
             let y = ray.origin.y + ray.direction.y * t;
             let z = ray.origin.z + ray.direction.z * t;
             x.powi(2) + z.powi(2) <= y.powi(2)
         }
-        fn intersect_caps(dcone: &DoubleCone, ray: &Ray) -> Vec<f64> {
+        fn intersect_caps(dcone: &DoubleCone, ray: &Ray) -> Hits {
             if !dcone.is_closed || ray.direction.y.abs() < EPSILON {
-                return vec![];
+                return Hits::new();
             }
             let min = -dcone.height / 2.0;
             let max = dcone.height / 2.0;
 
             let tmin = (min - ray.origin.y) / ray.direction.y;
             let tmax = (max - ray.origin.y) / ray.direction.y;
-            let mut res = vec![];
+            let mut res = Hits::new();
             if check_cap(ray, tmin) {
                 res.push(tmin);
             }
@@ -86,7 +88,7 @@ impl Intersectable for DoubleCone {
         let c = object_space_ray.origin.x.powi(2) - object_space_ray.origin.y.powi(2)
             + object_space_ray.origin.z.powi(2);
 
-        let mut res = vec![];
+        let mut res = Hits::new();
         if a.abs() < EPSILON {
             res.push(-c / (2.0 * b))
         } else {
@@ -145,3 +147,10 @@ impl IntoBody for DoubleCone {
         Body::DoubleCone(self.clone())
     }
 }
+
+encode_fields!(DoubleCone {
+    transform,
+    material,
+    height,
+    is_closed
+});
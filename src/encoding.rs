@@ -0,0 +1,260 @@
+use std::{
+    io::{Error, ErrorKind, Result},
+    sync::Arc,
+};
+
+/// Compact binary form of scene data, used to ship a ```World``` and ```Camera``` to render
+/// workers. Numbers are little endian, lists are prefixed with their length and enums with
+/// a one byte tag.
+/// ```
+/// use raytracer_rust::encoding::Encode;
+/// use raytracer_rust::tuple::Tuple;
+/// let mut bytes = vec![];
+/// Tuple::Point(1, 2, 3).encode(&mut bytes);
+/// assert_eq!(Tuple::decode(&mut bytes.as_slice()).unwrap(), Tuple::Point(1, 2, 3));
+/// ```
+pub trait Encode: Sized {
+    fn encode(&self, out: &mut Vec<u8>);
+    /// Reads a value from the front of ```bytes```, advancing past it.
+    fn decode(bytes: &mut &[u8]) -> Result<Self>;
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut out = vec![];
+        self.encode(&mut out);
+        out
+    }
+
+    /// Decodes a value that must take up all of ```bytes```.
+    fn from_bytes(mut bytes: &[u8]) -> Result<Self> {
+        let value = Self::decode(&mut bytes)?;
+        if !bytes.is_empty() {
+            return Err(invalid("trailing bytes after encoded value"));
+        }
+        Ok(value)
+    }
+}
+
+pub(crate) fn invalid(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg.to_string())
+}
+
+pub(crate) fn unknown_tag(kind: &str, tag: u8) -> Error {
+    invalid(&format!("unknown {kind} tag {tag}"))
+}
+
+fn take<'a>(bytes: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
+    if bytes.len() < len {
+        return Err(Error::new(
+            ErrorKind::UnexpectedEof,
+            "encoded data ends too early",
+        ));
+    }
+    let (head, tail) = bytes.split_at(len);
+    *bytes = tail;
+    Ok(head)
+}
+
+/// Implements ```Encode``` for a struct by encoding the listed fields in order.
+macro_rules! encode_fields {
+    ($t:ty { $($field:ident),* $(,)? }) => {
+        impl $crate::encoding::Encode for $t {
+            fn encode(&self, out: &mut Vec<u8>) {
+                $($crate::encoding::Encode::encode(&self.$field, out);)*
+            }
+
+            fn decode(bytes: &mut &[u8]) -> std::io::Result<Self> {
+                Ok(Self {
+                    $($field: $crate::encoding::Encode::decode(bytes)?,)*
+                })
+            }
+        }
+    };
+}
+
+/// Implements ```Encode``` for an enum whose variants each wrap a single value, tagging
+/// every variant with the given byte.
+macro_rules! encode_variants {
+    ($t:ident { $($tag:literal => $variant:ident),* $(,)? }) => {
+        impl $crate::encoding::Encode for $t {
+            fn encode(&self, out: &mut Vec<u8>) {
+                match self {
+                    $($t::$variant(inner) => {
+                        out.push($tag);
+                        $crate::encoding::Encode::encode(inner, out);
+                    })*
+                }
+            }
+
+            fn decode(bytes: &mut &[u8]) -> std::io::Result<Self> {
+                match <u8 as $crate::encoding::Encode>::decode(bytes)? {
+                    $($tag => Ok($t::$variant($crate::encoding::Encode::decode(bytes)?)),)*
+                    tag => Err($crate::encoding::unknown_tag(stringify!($t), tag)),
+                }
+            }
+        }
+    };
+}
+
+/// Implements ```Encode``` for an enum of unit variants, one byte per variant.
+macro_rules! encode_units {
+    ($t:ident { $($tag:literal => $variant:ident),* $(,)? }) => {
+        impl $crate::encoding::Encode for $t {
+            fn encode(&self, out: &mut Vec<u8>) {
+                out.push(match self {
+                    $($t::$variant => $tag,)*
+                });
+            }
+
+            fn decode(bytes: &mut &[u8]) -> std::io::Result<Self> {
+                match <u8 as $crate::encoding::Encode>::decode(bytes)? {
+                    $($tag => Ok($t::$variant),)*
+                    tag => Err($crate::encoding::unknown_tag(stringify!($t), tag)),
+                }
+            }
+        }
+    };
+}
+
+pub(crate) use {encode_fields, encode_units, encode_variants};
+
+macro_rules! encode_number {
+    ($($t:ty),*) => {$(
+        impl Encode for $t {
+            fn encode(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_le_bytes());
+            }
+
+            fn decode(bytes: &mut &[u8]) -> Result<Self> {
+                let raw = take(bytes, std::mem::size_of::<$t>())?;
+                Ok(<$t>::from_le_bytes(raw.try_into().unwrap()))
+            }
+        }
+    )*};
+}
+
+encode_number!(u8, u32, u64, f32, f64);
+
+impl Encode for usize {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (*self as u64).encode(out);
+    }
+
+    fn decode(bytes: &mut &[u8]) -> Result<Self> {
+        usize::try_from(u64::decode(bytes)?).map_err(|_| invalid("size does not fit in usize"))
+    }
+}
+
+impl Encode for bool {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (*self as u8).encode(out);
+    }
+
+    fn decode(bytes: &mut &[u8]) -> Result<Self> {
+        match u8::decode(bytes)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            tag => Err(unknown_tag("bool", tag)),
+        }
+    }
+}
+
+impl<T: Encode> Encode for Vec<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.len().encode(out);
+        for item in self {
+            item.encode(out);
+        }
+    }
+
+    fn decode(bytes: &mut &[u8]) -> Result<Self> {
+        let len = usize::decode(bytes)?;
+        // every item takes at least one byte, which bounds what a corrupt length can allocate
+        let mut items = Vec::with_capacity(len.min(bytes.len()));
+        for _ in 0..len {
+            items.push(T::decode(bytes)?);
+        }
+        Ok(items)
+    }
+}
+
+impl<T: Encode, const N: usize> Encode for [T; N] {
+    fn encode(&self, out: &mut Vec<u8>) {
+        for item in self {
+            item.encode(out);
+        }
+    }
+
+    fn decode(bytes: &mut &[u8]) -> Result<Self> {
+        let items = (0..N)
+            .map(|_| T::decode(bytes))
+            .collect::<Result<Vec<T>>>()?;
+        Ok(items.try_into().unwrap_or_else(|_| unreachable!()))
+    }
+}
+
+impl<T: Encode> Encode for Option<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.is_some().encode(out);
+        if let Some(value) = self {
+            value.encode(out);
+        }
+    }
+
+    fn decode(bytes: &mut &[u8]) -> Result<Self> {
+        match bool::decode(bytes)? {
+            true => Ok(Some(T::decode(bytes)?)),
+            false => Ok(None),
+        }
+    }
+}
+
+impl<T: Encode> Encode for Box<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.as_ref().encode(out);
+    }
+
+    fn decode(bytes: &mut &[u8]) -> Result<Self> {
+        Ok(Box::new(T::decode(bytes)?))
+    }
+}
+
+impl<T: Encode> Encode for Arc<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.as_ref().encode(out);
+    }
+
+    fn decode(bytes: &mut &[u8]) -> Result<Self> {
+        Ok(Arc::new(T::decode(bytes)?))
+    }
+}
+
+impl<A: Encode, B: Encode> Encode for (A, B) {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0.encode(out);
+        self.1.encode(out);
+    }
+
+    fn decode(bytes: &mut &[u8]) -> Result<Self> {
+        Ok((A::decode(bytes)?, B::decode(bytes)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_containers_and_rejects_bad_data() {
+        let value = (vec![Some(1.5f64), None], [true, false, true]);
+        let bytes = value.to_bytes();
+        assert_eq!(
+            <(Vec<Option<f64>>, [bool; 3])>::from_bytes(&bytes).unwrap(),
+            value
+        );
+
+        let err = Vec::<f64>::from_bytes(&bytes[..12]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+        assert!(bool::from_bytes(&[2]).is_err());
+        assert!(u8::from_bytes(&[1, 2]).is_err());
+    }
+}
@@ -0,0 +1,212 @@
+use crate::{
+    background::EnvironmentMap,
+    canvas::Sized,
+    color::{Color, RGB},
+    consts::PI,
+    encoding::Encode,
+    sampler::Sampler,
+    tuple::Tuple,
+};
+
+/// Lights the world from every direction using an equirectangular environment map.
+/// Directions are importance sampled by the luminance of the map, so bright areas
+/// such as the sun receive most of the samples.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnvironmentLight {
+    map: EnvironmentMap,
+    samples: usize,
+    width: usize,
+    height: usize,
+    // cumulative distribution of the rows, and of the texels within each row
+    marginal_cdf: Vec<f64>,
+    conditional_cdfs: Vec<Vec<f64>>,
+    total_weight: f64,
+}
+
+/// A direction towards the environment together with what it sees from there.
+#[derive(Clone, Copy, Debug)]
+pub struct EnvironmentSample {
+    pub direction: Tuple,
+    pub radiance: Color,
+    /// Probability density of picking ```direction```, per unit solid angle.
+    pub pdf: f64,
+}
+
+impl EnvironmentLight {
+    pub fn new(map: EnvironmentMap, samples: usize) -> Self {
+        let image = map.texture().image();
+        let (width, height) = (image.width(), image.height());
+        let mut conditional_cdfs = Vec::with_capacity(height);
+        let mut marginal_cdf = Vec::with_capacity(height);
+        let mut total_weight = 0.0;
+        for y in 0..height {
+            // rows near the poles cover less of the sphere
+            let latitude = (0.5 - (y as f64 + 0.5) / height as f64) * PI;
+            let mut row_weight = 0.0;
+            let cdf = (0..width)
+                .map(|x| {
+                    row_weight += luminance(image.color_at_pixel(x, y)) * latitude.cos();
+                    row_weight
+                })
+                .collect();
+            conditional_cdfs.push(cdf);
+            total_weight += row_weight;
+            marginal_cdf.push(total_weight);
+        }
+        Self {
+            map,
+            samples,
+            width,
+            height,
+            marginal_cdf,
+            conditional_cdfs,
+            total_weight,
+        }
+    }
+
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    pub fn map(&self) -> &EnvironmentMap {
+        &self.map
+    }
+
+    /// Picks a direction towards the environment, favouring its bright areas.
+    pub fn sample(&self, sampler: &mut Sampler) -> Option<EnvironmentSample> {
+        if self.total_weight <= 0.0 {
+            return None;
+        }
+        let (y, v_offset) = pick(&self.marginal_cdf, sampler.next_f64());
+        let (x, u_offset) = pick(&self.conditional_cdfs[y], sampler.next_f64());
+        let u = (x as f64 + u_offset) / self.width as f64;
+        let v = 1.0 - (y as f64 + v_offset) / self.height as f64;
+        let direction = EnvironmentMap::uv_to_direction(u, v);
+        let pdf = self.texel_pdf(x, y, v);
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some(EnvironmentSample {
+            direction,
+            radiance: self.map.color_in_direction(direction),
+            pdf,
+        })
+    }
+
+    /// Probability density of sampling ```direction```, per unit solid angle.
+    pub fn pdf(&self, direction: Tuple) -> f64 {
+        let (u, v) = EnvironmentMap::direction_to_uv(direction);
+        let x = ((u * self.width as f64) as usize).min(self.width - 1);
+        let y = (((1.0 - v) * self.height as f64) as usize).min(self.height - 1);
+        self.texel_pdf(x, y, v)
+    }
+
+    fn texel_pdf(&self, x: usize, y: usize, v: f64) -> f64 {
+        let row = &self.conditional_cdfs[y];
+        let weight = row[x] - if x > 0 { row[x - 1] } else { 0.0 };
+        let texel_probability = weight / self.total_weight;
+        let latitude = (v - 0.5) * PI;
+        // a texel spans 2π/width by π/height radians, shrunk by cos(latitude)
+        let texel_solid_angle =
+            (2.0 * PI / self.width as f64) * (PI / self.height as f64) * latitude.cos();
+        if texel_solid_angle <= 0.0 {
+            0.0
+        } else {
+            texel_probability / texel_solid_angle
+        }
+    }
+}
+
+pub fn luminance(color: Color) -> f64 {
+    0.2126 * color.red() + 0.7152 * color.green() + 0.0722 * color.blue()
+}
+
+/// Finds the bucket of a cumulative distribution that ```u``` falls into,
+/// along with where in the bucket it landed, in [0, 1).
+fn pick(cdf: &[f64], u: f64) -> (usize, f64) {
+    let total = *cdf.last().unwrap();
+    let target = u * total;
+    let index = cdf
+        .partition_point(|weight| *weight <= target)
+        .min(cdf.len() - 1);
+    let low = if index > 0 { cdf[index - 1] } else { 0.0 };
+    let width = cdf[index] - low;
+    let offset = if width > 0.0 {
+        ((target - low) / width).clamp(0.0, 1.0 - f64::EPSILON)
+    } else {
+        0.5
+    };
+    (index, offset)
+}
+
+/// Only the map and sample count are sent, the distributions are rebuilt on decoding.
+impl Encode for EnvironmentLight {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.map.encode(out);
+        self.samples.encode(out);
+    }
+
+    fn decode(bytes: &mut &[u8]) -> std::io::Result<Self> {
+        Ok(Self::new(
+            EnvironmentMap::decode(bytes)?,
+            usize::decode(bytes)?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        canvas::Canvas,
+        uv_pattern::{ImageTexture, TextureFilter, WrapMode},
+    };
+
+    fn map_from(image: Canvas) -> EnvironmentMap {
+        EnvironmentMap::new(
+            ImageTexture::new(image, TextureFilter::Nearest, WrapMode::Repeat),
+            1.0,
+        )
+    }
+
+    #[test]
+    fn samples_land_on_the_only_bright_texel() {
+        let mut image = Canvas::new(8, 4);
+        image.set_color_at_pixel(6, 1, Color::WHITE());
+        let light = EnvironmentLight::new(map_from(image), 16);
+        let mut sampler = Sampler::new(3);
+        for _ in 0..50 {
+            let sample = light.sample(&mut sampler).unwrap();
+            let (u, v) = EnvironmentMap::direction_to_uv(sample.direction);
+            assert_eq!((u * 8.0) as usize, 6);
+            assert_eq!(((1.0 - v) * 4.0) as usize, 1);
+            assert_eq!(sample.radiance, Color::WHITE());
+        }
+    }
+
+    #[test]
+    fn pdf_integrates_to_one_over_the_sphere() {
+        let mut image = Canvas::new(16, 8);
+        for y in 0..8 {
+            for x in 0..16 {
+                image.set_color_at_pixel(x, y, Color::new(x as f64, 1.0, y as f64));
+            }
+        }
+        let light = EnvironmentLight::new(map_from(image), 16);
+        let mut sampler = Sampler::new(11);
+        let n = 20000;
+        // E[1 / pdf] is the area of the unit sphere
+        let area = (0..n)
+            .map(|_| 1.0 / light.sample(&mut sampler).unwrap().pdf)
+            .sum::<f64>()
+            / n as f64;
+        assert!((area - 4.0 * PI).abs() < 0.02 * 4.0 * PI);
+    }
+
+    #[test]
+    fn black_map_gives_no_samples() {
+        let image = Canvas::new(2, 2);
+        let light = EnvironmentLight::new(map_from(image), 4);
+        assert!(light.sample(&mut Sampler::new(1)).is_none());
+    }
+}
@@ -1,8 +1,11 @@
 use crate::{
     body::{Body, Intersectable},
+    encoding::{encode_fields, encode_variants},
     intersections::Intersections,
     matrix::Matrix,
     ray::Ray,
+    stats,
+    transformation::Transformation,
 };
 
 #[derive(Clone, Debug)]
@@ -13,7 +16,7 @@ pub enum BodyOrGroup {
 
 #[derive(Clone, Debug)]
 pub struct Group {
-    transform: Matrix<4>,
+    transform: Transformation,
     items: Vec<BodyOrGroup>,
 }
 
@@ -28,10 +31,10 @@ impl Group {
     }
 
     pub fn transform(&self) -> Matrix<4> {
-        self.transform
+        self.transform.matrix()
     }
 
-    pub fn transform_mut(&mut self) -> &mut Matrix<4> {
+    pub fn transform_mut(&mut self) -> &mut Transformation {
         &mut self.transform
     }
 
@@ -43,14 +46,29 @@ impl Group {
         self.items.push(grp.into());
     }
 
-    pub fn intersect(&self, ray: &Ray) -> Intersections {
+    pub fn intersect(&self, ray: &Ray) -> Intersections<'_> {
         let mut xs = Intersections::default();
-        for item in self.items.iter() {
-            xs.extend(item.intersect(ray));
-        }
+        self.intersect_into(ray, &mut xs);
         xs.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
         xs
     }
+
+    /// Whether any item is hit within the ray's interval.
+    pub fn occludes(&self, ray: &Ray) -> bool {
+        stats::count_group_visit();
+        self.items.iter().any(|item| match item {
+            BodyOrGroup::Body(body) => body.occludes(ray),
+            BodyOrGroup::Group(group) => group.occludes(ray),
+        })
+    }
+
+    /// Adds the intersections with every item to ```xs```, unsorted.
+    pub fn intersect_into<'a>(&'a self, ray: &Ray, xs: &mut Intersections<'a>) {
+        stats::count_group_visit();
+        for item in self.items.iter() {
+            item.intersect_into(ray, xs);
+        }
+    }
 }
 
 impl GroupBuilder {
@@ -73,23 +91,36 @@ impl GroupBuilder {
     pub fn build(mut self) -> Group {
         let trans_inv = self.transform().inverse();
         self.items.iter_mut().for_each(|it| match it {
-            BodyOrGroup::Body(b) => *b.transform_mut() = trans_inv * b.transform(),
-            BodyOrGroup::Group(g) => *g.transform_mut() = trans_inv * g.transform(),
+            BodyOrGroup::Body(b) => {
+                let t = trans_inv * b.transform();
+                b.transform_mut().set(t)
+            }
+            BodyOrGroup::Group(g) => {
+                let t = trans_inv * g.transform();
+                g.transform_mut().set(t)
+            }
         });
         Group {
-            transform: self.transform(),
+            transform: self.transform().into(),
             items: self.items,
         }
     }
 }
 
 impl BodyOrGroup {
-    pub fn intersect(&self, ray: &Ray) -> Intersections {
+    pub fn intersect(&self, ray: &Ray) -> Intersections<'_> {
         match self {
             BodyOrGroup::Body(body) => body.intersect(ray),
             BodyOrGroup::Group(group) => Group::intersect(group, ray),
         }
     }
+
+    pub fn intersect_into<'a>(&'a self, ray: &Ray, xs: &mut Intersections<'a>) {
+        match self {
+            BodyOrGroup::Body(body) => body.intersect_into(ray, xs),
+            BodyOrGroup::Group(group) => group.intersect_into(ray, xs),
+        }
+    }
 }
 
 impl From<Body> for BodyOrGroup {
@@ -104,6 +135,9 @@ impl From<Group> for BodyOrGroup {
     }
 }
 
+encode_variants!(BodyOrGroup { 0 => Body, 1 => Group });
+encode_fields!(Group { transform, items });
+
 #[cfg(test)]
 mod tests {
 
@@ -166,7 +200,8 @@ mod tests {
         );
         grp.add_shape(s.into());
         let ray = Ray::new(Tuple::Point(10, 0, -10), Tuple::Vector(0, 0, 1));
-        let xs = grp.build().intersect(&ray);
+        let grp = grp.build();
+        let xs = grp.intersect(&ray);
         assert_eq!(xs.count(), 2);
     }
 
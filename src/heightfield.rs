@@ -0,0 +1,428 @@
+use std::sync::Arc;
+
+use crate::{
+    body::{Body, Hits, Intersectable, IntoBody},
+    bounds::BoundingBox,
+    canvas::{Canvas, Sized},
+    encoding::{invalid, Encode},
+    environment_light::luminance,
+    material::{Material, Phong},
+    matrix::Matrix,
+    ray::Ray,
+    transformation::Transformation,
+    tuple::Tuple,
+};
+
+/// Terrain from a grid of heights. In object space the grid spans 0 to 1 along x and z,
+/// and the heights are used as y directly, so scale it into place with the transform.
+/// The grid is shared between clones, so large terrains are cheap to pass around.
+#[derive(Clone, Debug)]
+pub struct Heightfield {
+    pub transform: Transformation,
+    pub material: Material,
+    columns: usize,
+    rows: usize,
+    heights: Arc<Vec<f64>>,
+    normals: Arc<Vec<Tuple>>,
+    bounds: BoundingBox,
+}
+
+impl PartialEq for Heightfield {
+    fn eq(&self, other: &Self) -> bool {
+        self.transform == other.transform
+            && self.material == other.material
+            && self.columns == other.columns
+            && self.rows == other.rows
+            && self.heights == other.heights
+    }
+}
+
+impl Heightfield {
+    /// ```heights``` holds ```rows``` rows of ```columns``` samples, rows running along z.
+    pub fn new(
+        transform: Matrix<4>,
+        material: Material,
+        columns: usize,
+        rows: usize,
+        heights: Vec<f64>,
+    ) -> Self {
+        assert!(
+            columns >= 2 && rows >= 2,
+            "a heightfield needs at least 2x2 samples"
+        );
+        assert_eq!(heights.len(), columns * rows);
+        let (low, high) = heights
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), h| {
+                (low.min(*h), high.max(*h))
+            });
+        let mut field = Self {
+            transform: transform.into(),
+            material,
+            columns,
+            rows,
+            heights: Arc::new(heights),
+            normals: Arc::new(vec![]),
+            bounds: BoundingBox::new(Tuple::Point(0.0, low, 0.0), Tuple::Point(1.0, high, 1.0)),
+        };
+        field.normals = Arc::new(field.vertex_normals());
+        field
+    }
+
+    /// Samples ```height(x, z)``` on a grid over [0, 1] x [0, 1], e.g. to build terrain from noise.
+    pub fn from_fn(columns: usize, rows: usize, height: impl Fn(f64, f64) -> f64) -> Self {
+        let heights = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .map(|(column, row)| {
+                height(
+                    column as f64 / (columns - 1) as f64,
+                    row as f64 / (rows - 1) as f64,
+                )
+            })
+            .collect();
+        Self::new(
+            Matrix::Identity(),
+            Material::Phong(Phong::default()),
+            columns,
+            rows,
+            heights,
+        )
+    }
+
+    /// Uses the brightness of each pixel as its height, from 0 for black to 1 for white.
+    /// Pixel columns run along x and pixel rows along z. Images smaller than 2x2 are rejected.
+    pub fn from_canvas(image: &Canvas) -> std::io::Result<Self> {
+        if image.width() < 2 || image.height() < 2 {
+            return Err(invalid(
+                "a heightfield needs an image of at least 2x2 pixels",
+            ));
+        }
+        let heights = (0..image.height())
+            .flat_map(|y| (0..image.width()).map(move |x| (x, y)))
+            .map(|(x, y)| luminance(image.color_at_pixel(x, y)))
+            .collect();
+        Ok(Self::new(
+            Matrix::Identity(),
+            Material::Phong(Phong::default()),
+            image.width(),
+            image.height(),
+            heights,
+        ))
+    }
+
+    pub fn load_ppm<T: AsRef<str>>(load_from: T) -> std::io::Result<Self> {
+        Self::from_canvas(&Canvas::load_ppm(load_from)?)
+    }
+
+    pub fn with_transform(mut self, t: Matrix<4>) -> Self {
+        self.transform.set(t);
+        self
+    }
+
+    pub fn with_material(mut self, m: Material) -> Self {
+        self.material = m;
+        self
+    }
+
+    fn height(&self, column: usize, row: usize) -> f64 {
+        self.heights[row * self.columns + column]
+    }
+
+    fn vertex(&self, column: usize, row: usize) -> Tuple {
+        Tuple::Point(
+            column as f64 * self.cell_width(),
+            self.height(column, row),
+            row as f64 * self.cell_depth(),
+        )
+    }
+
+    fn cell_width(&self) -> f64 {
+        1.0 / (self.columns - 1) as f64
+    }
+
+    fn cell_depth(&self) -> f64 {
+        1.0 / (self.rows - 1) as f64
+    }
+
+    fn vertex_normals(&self) -> Vec<Tuple> {
+        let slope = |before: usize, after: usize, height: &dyn Fn(usize) -> f64, spacing: f64| {
+            (height(after) - height(before)) / ((after - before) as f64 * spacing)
+        };
+        (0..self.rows)
+            .flat_map(|row| (0..self.columns).map(move |column| (column, row)))
+            .map(|(column, row)| {
+                let dx = slope(
+                    column.saturating_sub(1),
+                    (column + 1).min(self.columns - 1),
+                    &|c| self.height(c, row),
+                    self.cell_width(),
+                );
+                let dz = slope(
+                    row.saturating_sub(1),
+                    (row + 1).min(self.rows - 1),
+                    &|r| self.height(column, r),
+                    self.cell_depth(),
+                );
+                Tuple::Vector(-dx, 1.0, -dz).normalize()
+            })
+            .collect()
+    }
+
+    /// Distances at which the ray crosses the two triangles of a cell.
+    fn intersect_cell(&self, ray: &Ray, column: usize, row: usize, res: &mut Hits) {
+        let p00 = self.vertex(column, row);
+        let p10 = self.vertex(column + 1, row);
+        let p01 = self.vertex(column, row + 1);
+        let p11 = self.vertex(column + 1, row + 1);
+        for (a, b, c) in [(p00, p10, p11), (p00, p11, p01)] {
+            if let Some(t) = intersect_triangle(ray, a, b, c) {
+                res.push(t);
+            }
+        }
+    }
+}
+
+/// Möller–Trumbore, accepting hits at any distance along the ray.
+fn intersect_triangle(ray: &Ray, a: Tuple, b: Tuple, c: Tuple) -> Option<f64> {
+    // a little slack so rays through shared edges don't slip between triangles
+    const SLACK: f64 = 1e-9;
+    let edge1 = b - a;
+    let edge2 = c - a;
+    let p = ray.direction.cross(&edge2);
+    let determinant = edge1.dot(&p);
+    if determinant.abs() < 1e-12 {
+        return None;
+    }
+    let inverse = 1.0 / determinant;
+    let to_origin = ray.origin - a;
+    let u = to_origin.dot(&p) * inverse;
+    if !(-SLACK..=1.0 + SLACK).contains(&u) {
+        return None;
+    }
+    let q = to_origin.cross(&edge1);
+    let v = ray.direction.dot(&q) * inverse;
+    if v < -SLACK || u + v > 1.0 + SLACK {
+        return None;
+    }
+    Some(edge2.dot(&q) * inverse)
+}
+
+impl Intersectable for Heightfield {
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn transformation(&self) -> &Transformation {
+        &self.transform
+    }
+
+    fn transform_mut(&mut self) -> &mut Transformation {
+        &mut self.transform
+    }
+
+    fn intersect_in_object_space(&self, object_space_ray: &Ray) -> Hits {
+        let Some((t_enter, t_leave)) = self.bounds.intersection_range(object_space_ray) else {
+            return Hits::new();
+        };
+        // only walk the cells within the ray's interval
+        let t_enter = t_enter.max(object_space_ray.t_min);
+        let t_leave = t_leave.min(object_space_ray.t_max);
+        if t_enter > t_leave {
+            return Hits::new();
+        }
+        let (o, d) = (object_space_ray.origin, object_space_ray.direction);
+        let (width, depth) = (self.cell_width(), self.cell_depth());
+        let entry = object_space_ray.position(t_enter);
+        let mut column = ((entry.x / width).floor().max(0.0) as usize).min(self.columns - 2);
+        let mut row = ((entry.z / depth).floor().max(0.0) as usize).min(self.rows - 2);
+
+        // walk the cells the ray passes over, in order (2D DDA)
+        let axis = |origin: f64, direction: f64, cell: usize, size: f64| {
+            if direction > 0.0 {
+                (
+                    ((cell + 1) as f64 * size - origin) / direction,
+                    size / direction,
+                )
+            } else if direction < 0.0 {
+                ((cell as f64 * size - origin) / direction, -size / direction)
+            } else {
+                (f64::INFINITY, f64::INFINITY)
+            }
+        };
+        let (mut next_x, delta_x) = axis(o.x, d.x, column, width);
+        let (mut next_z, delta_z) = axis(o.z, d.z, row, depth);
+
+        let mut res = Hits::new();
+        loop {
+            self.intersect_cell(object_space_ray, column, row, &mut res);
+            if next_x < next_z {
+                if next_x > t_leave {
+                    break;
+                }
+                next_x += delta_x;
+                if d.x > 0.0 && column + 2 < self.columns {
+                    column += 1;
+                } else if d.x < 0.0 && column > 0 {
+                    column -= 1;
+                } else {
+                    break;
+                }
+            } else {
+                if next_z > t_leave {
+                    break;
+                }
+                next_z += delta_z;
+                if d.z > 0.0 && row + 2 < self.rows {
+                    row += 1;
+                } else if d.z < 0.0 && row > 0 {
+                    row -= 1;
+                } else {
+                    break;
+                }
+            }
+        }
+        res.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        // a ray through a shared edge reports it from both sides
+        res.dedup_by(|a, b| (*a - *b).abs() < 1e-9);
+        res
+    }
+
+    fn normal_at_in_object_space(&self, object_space_point: Tuple) -> Tuple {
+        let x = object_space_point.x.clamp(0.0, 1.0) / self.cell_width();
+        let z = object_space_point.z.clamp(0.0, 1.0) / self.cell_depth();
+        let column = (x.floor() as usize).min(self.columns - 2);
+        let row = (z.floor() as usize).min(self.rows - 2);
+        let (fx, fz) = (x - column as f64, z - row as f64);
+        let normal = |c: usize, r: usize| self.normals[(row + r) * self.columns + column + c];
+        // barycentric weights within the same triangles used for intersection
+        let interpolated = if fx >= fz {
+            normal(0, 0) * (1.0 - fx) + normal(1, 0) * (fx - fz) + normal(1, 1) * fz
+        } else {
+            normal(0, 0) * (1.0 - fz) + normal(1, 1) * fx + normal(0, 1) * (fz - fx)
+        };
+        interpolated.normalize()
+    }
+}
+
+impl From<Heightfield> for Body {
+    fn from(h: Heightfield) -> Self {
+        Body::Heightfield(h)
+    }
+}
+
+impl IntoBody for Heightfield {
+    fn into_body(&self) -> Body {
+        Body::Heightfield(self.clone())
+    }
+}
+
+/// Only the samples are sent, the normals and bounds are rebuilt on decoding.
+impl Encode for Heightfield {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.transform.encode(out);
+        self.material.encode(out);
+        self.columns.encode(out);
+        self.rows.encode(out);
+        self.heights.encode(out);
+    }
+
+    fn decode(bytes: &mut &[u8]) -> std::io::Result<Self> {
+        let transform = Matrix::decode(bytes)?;
+        let material = Material::decode(bytes)?;
+        let columns = usize::decode(bytes)?;
+        let rows = usize::decode(bytes)?;
+        let heights = Vec::decode(bytes)?;
+        if columns < 2 || rows < 2 || columns.checked_mul(rows) != Some(heights.len()) {
+            return Err(invalid("heightfield size does not match its samples"));
+        }
+        Ok(Self::new(transform, material, columns, rows, heights))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{noise::fractal, sampler::Sampler};
+
+    #[test]
+    fn flat_field_is_hit_at_its_height() {
+        let field = Heightfield::from_fn(4, 4, |_, _| 0.5);
+        let ray = Ray::new(Tuple::Point(0.3, 5.0, 0.7), Tuple::Vector(0.0, -1.0, 0.0));
+        assert_eq!(field.intersect_in_object_space(&ray), vec![4.5]);
+        assert_eq!(
+            field.normal_at_in_object_space(Tuple::Point(0.3, 0.5, 0.7)),
+            Tuple::Vector(0.0, 1.0, 0.0)
+        );
+        let outside = Ray::new(Tuple::Point(1.3, 5.0, 0.7), Tuple::Vector(0.0, -1.0, 0.0));
+        assert!(field.intersect_in_object_space(&outside).is_empty());
+    }
+
+    #[test]
+    fn ramp_is_hit_where_it_reaches_the_ray() {
+        let ramp = Heightfield::from_fn(5, 5, |x, _| x);
+        let ray = Ray::new(Tuple::Point(-1.0, 0.5, 0.4), Tuple::Vector(1.0, 0.0, 0.0));
+        let xs = ramp.intersect_in_object_space(&ray);
+        assert_eq!(xs.len(), 1);
+        assert!((xs[0] - 1.5).abs() < 1e-9);
+        assert_eq!(
+            ramp.normal_at_in_object_space(Tuple::Point(0.5, 0.5, 0.4)),
+            Tuple::Vector(-1.0, 1.0, 0.0).normalize()
+        );
+    }
+
+    #[test]
+    fn grid_walk_finds_the_same_hits_as_testing_every_cell() {
+        let field = Heightfield::from_fn(17, 13, |x, z| {
+            0.5 + 0.5 * fractal(Tuple::Point(x * 4.0, 0.0, z * 4.0), 3)
+        });
+        let mut sampler = Sampler::new(5);
+        let mut total_hits = 0;
+        for _ in 0..200 {
+            let mut random = |scale: f64| (sampler.next_f64() - 0.5) * scale;
+            let origin = Tuple::Point(0.5 + random(3.0), 0.5 + random(3.0), 0.5 + random(3.0));
+            let target = Tuple::Point(0.5 + random(1.0), 0.5 + random(0.5), 0.5 + random(1.0));
+            let ray = Ray::new(origin, target - origin);
+
+            let mut expected = Hits::new();
+            for row in 0..field.rows - 1 {
+                for column in 0..field.columns - 1 {
+                    field.intersect_cell(&ray, column, row, &mut expected);
+                }
+            }
+            expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            expected.dedup_by(|a, b| (*a - *b).abs() < 1e-9);
+
+            let actual = field.intersect_in_object_space(&ray);
+            total_hits += actual.len();
+            assert_eq!(actual, expected);
+        }
+        assert!(total_hits > 100);
+    }
+
+    #[test]
+    fn heights_come_from_pixel_brightness() {
+        let image = Canvas::from_ppm(b"P3\n2 2\n255\n0 0 0 255 255 255\n0 0 0 0 0 0\n").unwrap();
+        let field = Heightfield::from_canvas(&image).unwrap();
+        // the white pixel is the far corner of the first row: x = 1, z = 0
+        let ray = Ray::new(Tuple::Point(1.0, 5.0, 0.0), Tuple::Vector(0.0, -1.0, 0.0));
+        let xs = field.intersect_in_object_space(&ray);
+        assert_eq!(xs.len(), 1);
+        assert!((xs[0] - 4.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn images_narrower_than_two_pixels_are_rejected() {
+        for ppm in [
+            &b"P3\n1 3\n255\n0 0 0 0 0 0 0 0 0\n"[..],
+            b"P3\n3 1\n255\n0 0 0 0 0 0 0 0 0\n",
+        ] {
+            let image = Canvas::from_ppm(ppm).unwrap();
+            let err = Heightfield::from_canvas(&image).unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        }
+    }
+}
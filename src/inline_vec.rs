@@ -0,0 +1,227 @@
+use std::{
+    fmt::Debug,
+    iter::{Chain, Flatten},
+    ops::Index,
+};
+
+/// List that keeps up to ```N``` items on the stack and only moves them to the heap
+/// when it grows past that, so the usual handful of hits per ray never allocates.
+/// ```
+/// use raytracer_rust::inline_vec::InlineVec;
+/// let mut xs: InlineVec<f64, 2> = InlineVec::new();
+/// xs.push(3.0);
+/// xs.push(1.0);
+/// xs.push(2.0);
+/// xs.sort_by(|a, b| a.partial_cmp(b).unwrap());
+/// assert_eq!(xs, vec![1.0, 2.0, 3.0]);
+/// ```
+#[derive(Clone)]
+pub struct InlineVec<T: Copy, const N: usize> {
+    inline_len: usize,
+    inline: [Option<T>; N],
+    // holds every item once the inline storage has overflowed
+    spilled: Vec<T>,
+}
+
+impl<T: Copy, const N: usize> InlineVec<T, N> {
+    pub fn new() -> Self {
+        Self {
+            inline_len: 0,
+            inline: [None; N],
+            spilled: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, item: T) {
+        if !self.spilled.is_empty() {
+            self.spilled.push(item);
+        } else if self.inline_len < N {
+            self.inline[self.inline_len] = Some(item);
+            self.inline_len += 1;
+        } else {
+            self.spilled.reserve(2 * N);
+            self.spilled.extend(self.inline.iter().flatten());
+            self.spilled.push(item);
+            self.inline = [None; N];
+            self.inline_len = 0;
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.inline_len + self.spilled.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if self.spilled.is_empty() {
+            self.inline[..self.inline_len].get(index)?.as_ref()
+        } else {
+            self.spilled.get(index)
+        }
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.get(self.len().checked_sub(1)?)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.inline[..self.inline_len]
+            .iter()
+            .flatten()
+            .chain(self.spilled.iter())
+    }
+
+    pub fn remove(&mut self, index: usize) -> T {
+        if self.spilled.is_empty() {
+            assert!(index < self.inline_len, "index {index} out of bounds");
+            let item = self.inline[index].take().unwrap();
+            self.inline[index..self.inline_len].rotate_left(1);
+            self.inline_len -= 1;
+            item
+        } else {
+            self.spilled.remove(index)
+        }
+    }
+
+    pub fn retain(&mut self, mut keep: impl FnMut(&T) -> bool) {
+        if self.spilled.is_empty() {
+            let mut kept = 0;
+            for i in 0..self.inline_len {
+                let item = self.inline[i].take().unwrap();
+                if keep(&item) {
+                    self.inline[kept] = Some(item);
+                    kept += 1;
+                }
+            }
+            self.inline_len = kept;
+        } else {
+            self.spilled.retain(keep);
+        }
+    }
+
+    /// Removes consecutive items that ```same``` considers equal, keeping the first.
+    pub fn dedup_by(&mut self, mut same: impl FnMut(&T, &T) -> bool) {
+        let mut previous: Option<T> = None;
+        self.retain(|item| {
+            let duplicate = previous.is_some_and(|p| same(item, &p));
+            if !duplicate {
+                previous = Some(*item);
+            }
+            !duplicate
+        });
+    }
+
+    /// Sorts without allocating; equal items may be reordered.
+    pub fn sort_by(&mut self, mut compare: impl FnMut(&T, &T) -> std::cmp::Ordering) {
+        if self.spilled.is_empty() {
+            self.inline[..self.inline_len]
+                .sort_unstable_by(|a, b| compare(a.as_ref().unwrap(), b.as_ref().unwrap()));
+        } else {
+            self.spilled.sort_unstable_by(compare);
+        }
+    }
+
+    pub fn contains(&self, item: &T) -> bool
+    where
+        T: PartialEq,
+    {
+        self.iter().any(|x| x == item)
+    }
+
+    pub fn to_vec(&self) -> Vec<T> {
+        self.iter().copied().collect()
+    }
+}
+
+impl<T: Copy, const N: usize> Default for InlineVec<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Copy, const N: usize> Index<usize> for InlineVec<T, N> {
+    type Output = T;
+    fn index(&self, index: usize) -> &Self::Output {
+        self.get(index)
+            .unwrap_or_else(|| panic!("index {index} out of bounds for length {}", self.len()))
+    }
+}
+
+impl<T: Copy, const N: usize> Extend<T> for InlineVec<T, N> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, items: I) {
+        for item in items {
+            self.push(item);
+        }
+    }
+}
+
+impl<T: Copy, const N: usize> IntoIterator for InlineVec<T, N> {
+    type Item = T;
+    type IntoIter = Chain<Flatten<std::array::IntoIter<Option<T>, N>>, std::vec::IntoIter<T>>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.inline.into_iter().flatten().chain(self.spilled)
+    }
+}
+
+impl<T: Copy, const N: usize> FromIterator<T> for InlineVec<T, N> {
+    fn from_iter<I: IntoIterator<Item = T>>(items: I) -> Self {
+        let mut res = Self::new();
+        res.extend(items);
+        res
+    }
+}
+
+impl<T: Copy, const N: usize, const K: usize> From<[T; K]> for InlineVec<T, N> {
+    fn from(items: [T; K]) -> Self {
+        items.into_iter().collect()
+    }
+}
+
+impl<T: Copy + PartialEq, const N: usize> PartialEq<Vec<T>> for InlineVec<T, N> {
+    fn eq(&self, other: &Vec<T>) -> bool {
+        self.len() == other.len() && self.iter().zip(other).all(|(a, b)| a == b)
+    }
+}
+
+impl<T: Copy + PartialEq, const N: usize> PartialEq for InlineVec<T, N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().zip(other.iter()).all(|(a, b)| a == b)
+    }
+}
+
+impl<T: Copy + Debug, const N: usize> Debug for InlineVec<T, N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spills_to_the_heap_and_keeps_order() {
+        let mut xs: InlineVec<usize, 3> = InlineVec::new();
+        xs.extend(0..3);
+        assert!(xs.spilled.is_empty());
+        xs.extend(3..6);
+        assert_eq!(xs, vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(xs.remove(1), 1);
+        xs.retain(|x| x % 2 == 0);
+        assert_eq!(xs, vec![0, 2, 4]);
+        assert_eq!(xs.last(), Some(&4));
+    }
+
+    #[test]
+    fn removes_and_dedups_inline() {
+        let mut xs = InlineVec::<f64, 8>::from([1.0, 1.0, 2.0, 3.0, 3.0]);
+        xs.dedup_by(|a, b| a == b);
+        assert_eq!(xs, vec![1.0, 2.0, 3.0]);
+        assert_eq!(xs.remove(0), 1.0);
+        assert_eq!(xs, vec![2.0, 3.0]);
+        assert_eq!(xs[1], 3.0);
+    }
+}
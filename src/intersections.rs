@@ -1,30 +1,39 @@
 use crate::{
     body::{Body, Intersectable},
     computed_intersection::ComputedIntersection,
-    material::Refractive,
+    inline_vec::InlineVec,
+    material::{Material, Refractive},
     ray::Ray,
 };
 use std::ops::Index;
 
-#[derive(Debug, Clone)]
-pub struct Intersection {
+/// Intersections a ray can collect before they are moved to the heap.
+pub const INLINE_INTERSECTIONS: usize = 32;
+
+/// Bodies a ray can be inside of at once before the container stack is moved to the heap.
+const INLINE_CONTAINERS: usize = 16;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Intersection<'a> {
     pub t: f64,
-    pub body: Body,
+    pub body: &'a Body,
     pub ray: Ray,
 }
 
 #[derive(Debug, Default, Clone)]
-pub struct Intersections {
-    // count: u8,
-    data: Vec<Intersection>,
+pub struct Intersections<'a> {
+    data: InlineVec<Intersection<'a>, INLINE_INTERSECTIONS>,
+    /// Only intersections past this distance can be the ```hit```; the ones before it are
+    /// still kept, since refraction needs them to know which bodies the ray is inside of.
+    pub t_min: f64,
 }
 
-impl Intersection {
-    pub fn new(t: f64, body: Body, ray: Ray) -> Self {
+impl<'a> Intersection<'a> {
+    pub fn new(t: f64, body: &'a Body, ray: Ray) -> Self {
         Self { t, body, ray }
     }
 
-    pub fn as_computed(&self, mu_from: f32, mu_to: f32) -> ComputedIntersection {
+    pub fn as_computed(&self, mu_from: f32, mu_to: f32) -> ComputedIntersection<'a> {
         let position = self.ray.position(self.t);
         let mut normalv = self.body.normal_at(position);
         let eyev = -self.ray.direction;
@@ -34,28 +43,31 @@ impl Intersection {
         }
         let reflectv = self.ray.direction.reflect(normalv);
         ComputedIntersection::new(
-            inside, position, self.body.clone(), eyev, normalv, reflectv, mu_from, mu_to,
+            inside, position, self.body, eyev, normalv, reflectv, mu_from, mu_to,
         )
     }
 }
 
-impl PartialEq for Intersection {
+impl PartialEq for Intersection<'_> {
     fn eq(&self, other: &Self) -> bool {
         self.t == other.t
     }
 }
 
-impl Intersections {
-    pub fn new(i: Vec<Intersection>) -> Self {
-        Self { data: i }
+impl<'a> Intersections<'a> {
+    pub fn new(i: Vec<Intersection<'a>>) -> Self {
+        Self {
+            data: i.into_iter().collect(),
+            t_min: 0.0,
+        }
     }
 
-    pub fn insert(&mut self, i: Intersection) {
+    pub fn insert(&mut self, i: Intersection<'a>) {
         self.data.push(i);
     }
 
-    pub fn extend(&mut self, xs: Intersections) {
-        self.data.extend(xs.data.into_iter());
+    pub fn extend(&mut self, xs: Intersections<'a>) {
+        self.data.extend(xs.data.iter().copied());
     }
 
     pub fn count(&self) -> usize {
@@ -66,11 +78,15 @@ impl Intersections {
         self.data.sort_by(f);
     }
 
+    pub fn retain(&mut self, keep: impl FnMut(&Intersection<'a>) -> bool) {
+        self.data.retain(keep);
+    }
+
     pub fn filter_nan(&mut self) {
         self.data.retain(|x| !x.t.is_nan());
     }
 
-    pub fn iter(&self) -> std::slice::Iter<Intersection> {
+    pub fn iter(&self) -> impl Iterator<Item = &Intersection<'a>> {
         self.data.iter()
     }
 
@@ -78,15 +94,19 @@ impl Intersections {
     /// use raytracer_rust::sphere::Sphere;
     /// use raytracer_rust::tuple::Tuple;
     /// use raytracer_rust::ray::Ray;
+    /// use raytracer_rust::body::Body;
     /// use raytracer_rust::intersections::{Intersection, Intersections};
-    /// let i1 = Intersection::new(1.0, Sphere::default().into(), Ray::new(Tuple::Point(0.0, 0.0, -5.0), Tuple::Vector(0.0, 0.0, 1.0)));
-    /// let i2 = Intersection::new(2.0, Sphere::default().into(), Ray::new(Tuple::Point(0.0, 0.0, -5.0), Tuple::Vector(0.0, 0.0, 1.0)));
-    /// let xs = Intersections::new(vec![i1, i2]);
+    /// let sphere: Body = Sphere::default().into();
+    /// let i1 = Intersection::new(1.0, &sphere, Ray::new(Tuple::Point(0.0, 0.0, -5.0), Tuple::Vector(0.0, 0.0, 1.0)));
+    /// let i2 = Intersection::new(2.0, &sphere, Ray::new(Tuple::Point(0.0, 0.0, -5.0), Tuple::Vector(0.0, 0.0, 1.0)));
+    /// let mut xs = Intersections::new(vec![i1, i2]);
     /// assert_eq!(xs.hit(), Some(&i1));
+    /// xs.t_min = 1.0;
+    /// assert_eq!(xs.hit(), Some(&i2));
     /// ```
-    pub fn hit(&self) -> Option<&Intersection> {
+    pub fn hit(&self) -> Option<&Intersection<'a>> {
         for intersection in self.data.iter() {
-            if intersection.t > 0.0 {
+            if intersection.t > self.t_min {
                 return Some(intersection);
             }
         }
@@ -95,32 +115,47 @@ impl Intersections {
 
     // FIXME: comeup with better algorithm.
     pub fn get_mu_shift(&self, intersection: &Intersection) -> (f32, f32) {
-        let mut containers: Vec<&Body> = vec![];
-        let (mut mu_from, mut mu_to) = (1.0, 1.0);
+        self.get_material_shift(intersection, 1.0, |m| m.refractive_index())
+    }
+
+    /// Abbe numbers of the media on either side of ```intersection```, ```0.0``` being vacuum.
+    pub fn get_abbe_shift(&self, intersection: &Intersection) -> (f32, f32) {
+        self.get_material_shift(intersection, 0.0, |m| m.abbe_number())
+    }
+
+    fn get_material_shift(
+        &self,
+        intersection: &Intersection,
+        outside: f32,
+        property: impl Fn(&Material) -> f32,
+    ) -> (f32, f32) {
+        let mut containers: InlineVec<&Body, INLINE_CONTAINERS> = InlineVec::new();
+        let (mut from, mut to) = (outside, outside);
         for i in self.data.iter() {
             if i == intersection && !containers.is_empty() {
-                mu_from = containers.last().unwrap().material().refractive_index();
+                from = property(containers.last().unwrap().material());
             }
-            if containers.contains(&&i.body) {
-                let index = containers.iter().position(|x| x == &&i.body).unwrap();
-                containers.remove(index);
-            } else {
-                containers.push(&i.body);
+            let position = containers.iter().position(|x| std::ptr::eq(*x, i.body));
+            match position {
+                Some(index) => {
+                    containers.remove(index);
+                }
+                None => containers.push(i.body),
             }
 
             if i == intersection {
                 if !containers.is_empty() {
-                    mu_to = containers.last().unwrap().material().refractive_index();
+                    to = property(containers.last().unwrap().material());
                 };
                 break;
             }
         }
-        (mu_from, mu_to)
+        (from, to)
     }
 }
 
-impl Index<usize> for Intersections {
-    type Output = Intersection;
+impl<'a> Index<usize> for Intersections<'a> {
+    type Output = Intersection<'a>;
     fn index(&self, index: usize) -> &Self::Output {
         &self.data[index]
     }
@@ -129,9 +164,7 @@ impl Index<usize> for Intersections {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{
-        body::Intersectable, material::Phong, matrix::Matrix, sphere::Sphere, tuple::Tuple,
-    };
+    use crate::{material::Phong, matrix::Matrix, sphere::Sphere, tuple::Tuple};
 
     #[test]
     fn mu_shift_works() {
@@ -158,6 +191,7 @@ mod tests {
         );
         let r = Ray::new(Tuple::Point(0.0, 0.0, -5.0), Tuple::Vector(0.0, 0.0, 1.0));
 
+        let (s4, sdown, sup) = (Body::from(s4), Body::from(sdown), Body::from(sup));
         let xs4 = s4.intersect(&r);
         let xsdown = sdown.intersect(&r);
         let xsup = sup.intersect(&r);
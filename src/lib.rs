@@ -1,21 +1,40 @@
+pub mod background;
 pub mod body;
+pub mod bounds;
 pub mod camera;
 pub mod canvas;
 pub mod color;
 pub mod computed_intersection;
+pub mod cone;
 pub mod cube;
 pub mod cylinder;
+pub mod disk;
+pub mod distributed;
 pub mod double_cone;
+pub mod encoding;
+pub mod environment_light;
 pub mod group;
+pub mod heightfield;
+pub mod inline_vec;
 pub mod intersections;
 pub mod material;
 pub mod matrix;
+pub mod noise;
+pub mod normal_map;
 pub mod pattern;
 pub mod plane;
 pub mod point_light;
+pub mod quadric;
 pub mod ray;
+pub mod sampler;
+pub mod sdf;
+pub mod solver;
 pub mod sphere;
+pub mod stats;
+pub mod torus;
+pub mod transformation;
 pub mod tuple;
+pub mod uv_pattern;
 pub mod world;
 
 #[allow(dead_code)]
@@ -27,6 +46,11 @@ pub mod consts {
     pub const PI_BY_4: f64 = std::f64::consts::FRAC_PI_4;
     pub const PI_BY_6: f64 = std::f64::consts::FRAC_PI_6;
     pub const SQRT_2: f64 = std::f64::consts::SQRT_2;
+    // Fraunhofer C, d and F lines (in micrometres), used as the wavelengths
+    // of the red, green and blue channels when tracing dispersion.
+    pub const WAVELENGTH_RED: f64 = 0.6563;
+    pub const WAVELENGTH_GREEN: f64 = 0.5876;
+    pub const WAVELENGTH_BLUE: f64 = 0.4861;
 }
 
 pub trait RoundToNDecimalPlaces {
@@ -87,14 +111,14 @@ use crate::{
     world::World,
 };
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
-use std::{sync::Mutex, time::Instant};
+use std::{net::TcpListener, process::Command, sync::Mutex, time::Instant};
 
 pub fn chapter6_challenge() {
     println!("Chapter 6 challenge without multi-threading ...");
     let now = Instant::now();
     let width: usize = 800;
     let mut canvas = Canvas::new(width, width);
-    let mut s = Sphere::default();
+    let mut s: Body = Sphere::default().into();
     match s.material_mut() {
         // Material::Phong(p) => p.color = Color::new(1.0, 0.2, 1.0),
         Material::Phong(p) => p.pattern = Pattern::Flat(Flat::new(Color::new(1.0, 0.2, 1.0))),
@@ -123,7 +147,7 @@ pub fn chapter6_challenge() {
                 let color = hit
                     .body
                     .material()
-                    .lighting(&hit.body, light, point, eye, normal, 0.0);
+                    .lighting(hit.body, light, point, eye, normal, 0.0);
 
                 canvas.set_color_at_pixel(i, j, color);
             }
@@ -143,7 +167,7 @@ pub fn chapter6_challenge_parallel() {
     let now = Instant::now();
     let width: usize = 800;
     let world = Mutex::new(Canvas::new(width, width));
-    let mut s = Sphere::default();
+    let mut s: Body = Sphere::default().into();
     match s.material_mut() {
         // Material::Phong(p) => p.color = Color::new(1.0, 0.2, 1.0),
         Material::Phong(p) => p.pattern = Pattern::Flat(Flat::new(Color::new(1.0, 0.2, 1.0))),
@@ -172,7 +196,7 @@ pub fn chapter6_challenge_parallel() {
                 let color = hit
                     .body
                     .material()
-                    .lighting(&hit.body, light, point, eye, normal, 0.0);
+                    .lighting(hit.body, light, point, eye, normal, 0.0);
 
                 // Introduced lock in another scope to unlock the variable just after completion of this command
                 // and not wait until the whole block to complete execution
@@ -211,7 +235,7 @@ fn chapter7_setup() -> (World, Camera) {
                 * Matrix::rotation_X(consts::PI_BY_2)
                 * Matrix::Scaling(10.0, 0.01, 10.0),
         )
-        .with_material(*floor.material());
+        .with_material(floor.material().clone());
 
     let right_wall = Sphere::default()
         .with_transform(
@@ -220,7 +244,7 @@ fn chapter7_setup() -> (World, Camera) {
                 * Matrix::rotation_X(consts::PI_BY_2)
                 * Matrix::Scaling(10.0, 0.01, 10.0),
         )
-        .with_material(*floor.material());
+        .with_material(floor.material().clone());
 
     let middle = Sphere::default()
         .with_transform(Matrix::Translation(-0.5, 1.0, 0.5))
@@ -297,6 +321,66 @@ pub fn chapter7_challenge_parallel() {
     println!("time taken: {} ms", elapsed.as_millis());
 }
 
+/// Renders the chapter 7 scene and prints what was traced and where the time went.
+pub fn chapter7_challenge_with_stats() {
+    println!("Chapter 7 challenge with render statistics ...");
+    let now = Instant::now();
+    let (world, camera) = chapter7_setup();
+    let setup = now.elapsed();
+    let (canvas, mut stats) = camera.render_with_stats(&world);
+    let now = Instant::now();
+    canvas.save_as_ppm("challenges/ch7.ppm").unwrap();
+    stats.phases.insert(0, ("setup", setup));
+    stats.phases.push(("save", now.elapsed()));
+    println!("{stats}");
+}
+
+/// Renders the chapter 7 scene on ```workers``` copies of this program, started with the
+/// ```--worker``` flag and connected over localhost.
+pub fn chapter7_challenge_distributed(workers: usize) {
+    println!("Chapter 7 challenge with {workers} worker processes ...");
+    let now = Instant::now();
+    let (world, camera) = chapter7_setup();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let program = std::env::current_exe().unwrap();
+    let mut children: Vec<_> = (0..workers)
+        .map(|_| {
+            Command::new(&program)
+                .args(["--worker", &address])
+                .spawn()
+                .unwrap()
+        })
+        .collect();
+    distributed::render_distributed(&camera, &world, &listener)
+        .unwrap()
+        .save_as_ppm("challenges/ch7.ppm")
+        .unwrap();
+    for child in children.iter_mut() {
+        child.wait().unwrap();
+    }
+    let elapsed = now.elapsed();
+    println!("time taken: {} ms", elapsed.as_millis());
+}
+
+/// Renders the chapter 7 scene with every render method and prints how long each took.
+pub fn render_benchmark() {
+    println!("Render benchmark on the chapter 7 scene ...");
+    let (world, camera) = chapter7_setup();
+    let time = |name: &str, render: &dyn Fn() -> Canvas| {
+        let now = Instant::now();
+        render();
+        let elapsed = now.elapsed();
+        println!("{name}: time taken: {} ms", elapsed.as_millis());
+    };
+    time("render", &|| camera.render(&world));
+    time("render_par", &|| camera.render_par(&world));
+    time("render_par_impling_rayon_traits", &|| {
+        camera.render_par_impling_rayon_traits(&world)
+    });
+    time("render_tiled", &|| camera.render_tiled(&world));
+}
+
 pub fn chapter9_challenge() {
     println!("Chapter 9 challenge with multi-threading ...");
     let now = Instant::now();
@@ -1,8 +1,32 @@
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(String::as_str) {
+        // started by chapter7_challenge_distributed to render tiles for a coordinator
+        Some("--worker") => {
+            let coordinator = args.get(2).expect("usage: --worker <coordinator address>");
+            raytracer_rust::distributed::work(coordinator).expect("worker failed");
+            return;
+        }
+        Some("--stats") => {
+            raytracer_rust::chapter7_challenge_with_stats();
+            return;
+        }
+        Some("--workers") => {
+            let workers = args
+                .get(2)
+                .and_then(|n| n.parse().ok())
+                .expect("usage: --workers <count>");
+            raytracer_rust::chapter7_challenge_distributed(workers);
+            return;
+        }
+        _ => {}
+    }
+
     // raytracer_rust::chapter6_challenge();
     // raytracer_rust::chapter6_challenge_parallel();
     // raytracer_rust::chapter7_challenge();
     // raytracer_rust::chapter7_challenge_parallel();
+    // raytracer_rust::render_benchmark();
     // raytracer_rust::chapter9_challenge();
     // raytracer_rust::chapter10_challenge();
     // raytracer_rust::chapter11_challenge();
@@ -1,18 +1,21 @@
 use crate::{
     body::Body,
     color::Color,
-    matrix::Matrix,
+    consts::{WAVELENGTH_BLUE, WAVELENGTH_GREEN, WAVELENGTH_RED},
+    encoding::{encode_fields, encode_variants},
+    normal_map::NormalPerturbation,
     pattern::{Pattern, Stencil},
     point_light::PointLight,
+    transformation::Transformation,
     tuple::Tuple,
 };
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Material {
     Phong(Phong),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Phong {
     // pub color: Color,
     pub pattern: Pattern,
@@ -23,6 +26,10 @@ pub struct Phong {
     pub reflectiveness: f32,
     pub transparency: f32,
     pub refractive_index: f32,
+    /// Abbe number of the material, `0.0` means the material does not disperse light.
+    pub abbe_number: f32,
+    /// Bump or normal map tilting the shading normal, `None` shades with the geometric normal.
+    pub normal_perturbation: Option<NormalPerturbation>,
 }
 
 pub trait Reflective {
@@ -37,9 +44,43 @@ impl Reflective for Material {
     }
 }
 
+impl Material {
+    pub fn normal_perturbation(&self) -> Option<&NormalPerturbation> {
+        match self {
+            Material::Phong(p) => p.normal_perturbation.as_ref(),
+        }
+    }
+}
+
 pub trait Refractive {
     fn refractive_index(&self) -> f32;
     fn transparency(&self) -> f32;
+    fn abbe_number(&self) -> f32;
+
+    fn refractive_index_at(&self, wavelength: f64) -> f32 {
+        cauchy_refractive_index(self.refractive_index(), self.abbe_number(), wavelength)
+    }
+}
+
+/// Refractive index at `wavelength` (in micrometres) of a material with the given
+/// refractive index at the d line and abbe number, using Cauchy's equation `n = A + B / λ²`.
+/// ```
+/// use raytracer_rust::material::cauchy_refractive_index;
+/// use raytracer_rust::consts::{WAVELENGTH_RED, WAVELENGTH_GREEN, WAVELENGTH_BLUE};
+/// assert!((cauchy_refractive_index(1.5168, 64.17, WAVELENGTH_GREEN) - 1.5168).abs() < 1e-6);
+/// assert!(cauchy_refractive_index(1.5168, 64.17, WAVELENGTH_RED) < 1.5168);
+/// assert!(cauchy_refractive_index(1.5168, 64.17, WAVELENGTH_BLUE) > 1.5168);
+/// assert_eq!(cauchy_refractive_index(1.5, 0.0, WAVELENGTH_BLUE), 1.5);
+/// ```
+pub fn cauchy_refractive_index(refractive_index: f32, abbe_number: f32, wavelength: f64) -> f32 {
+    if abbe_number <= 0.0 {
+        return refractive_index;
+    }
+    let n_d = refractive_index as f64;
+    let b =
+        (n_d - 1.0) / (abbe_number as f64 * (WAVELENGTH_BLUE.powi(-2) - WAVELENGTH_RED.powi(-2)));
+    let a = n_d - b / WAVELENGTH_GREEN.powi(2);
+    (a + b / wavelength.powi(2)) as f32
 }
 
 impl Refractive for Material {
@@ -54,6 +95,12 @@ impl Refractive for Material {
             Material::Phong(p) => p.transparency,
         }
     }
+
+    fn abbe_number(&self) -> f32 {
+        match self {
+            Material::Phong(p) => p.abbe_number,
+        }
+    }
 }
 
 pub trait PhongLighting {
@@ -66,6 +113,17 @@ pub trait PhongLighting {
         normalv: Tuple,
         transparency_factor: f64,
     ) -> Color;
+
+    /// Diffuse and specular light reaching ```point``` from each (light vector, radiance) pair,
+    /// without any ambient term.
+    fn lighting_from_directions(
+        &self,
+        body: &Body,
+        point: Tuple,
+        eyev: Tuple,
+        normalv: Tuple,
+        lights: impl IntoIterator<Item = (Tuple, Color)>,
+    ) -> Color;
 }
 
 impl PhongLighting for Material {
@@ -84,6 +142,21 @@ impl PhongLighting for Material {
             }
         }
     }
+
+    fn lighting_from_directions(
+        &self,
+        body: &Body,
+        point: Tuple,
+        eyev: Tuple,
+        normalv: Tuple,
+        lights: impl IntoIterator<Item = (Tuple, Color)>,
+    ) -> Color {
+        match self {
+            Material::Phong(phong) => {
+                phong.lighting_from_directions(body, point, eyev, normalv, lights)
+            }
+        }
+    }
 }
 
 impl Phong {
@@ -111,6 +184,16 @@ impl Phong {
         self.reflectiveness = c;
         self
     }
+
+    pub fn with_abbe_number(mut self, c: f32) -> Self {
+        self.abbe_number = c;
+        self
+    }
+
+    pub fn with_normal_perturbation(mut self, perturbation: impl Into<NormalPerturbation>) -> Self {
+        self.normal_perturbation = Some(perturbation.into());
+        self
+    }
 }
 
 impl Default for Phong {
@@ -124,6 +207,8 @@ impl Default for Phong {
             reflectiveness: 0.0,
             transparency: 0.0,
             refractive_index: 1.0,
+            abbe_number: 0.0,
+            normal_perturbation: None,
         }
     }
 }
@@ -138,10 +223,41 @@ impl PhongLighting for Phong {
         normalv: Tuple,
         transparency_factor: f64,
     ) -> Color {
-        let effective_color = self.color_at(body, point) * light.intensity;
-        let ambient = effective_color * self.ambient;
-
+        let surface_color = self.color_at(body, point);
+        let ambient = surface_color * light.intensity * self.ambient;
         let lightv = (light.position - point).normalize();
+        let diffuse_and_specular =
+            self.diffuse_and_specular(surface_color, lightv, light.intensity, eyev, normalv);
+        ambient + diffuse_and_specular * transparency_factor
+    }
+
+    fn lighting_from_directions(
+        &self,
+        body: &Body,
+        point: Tuple,
+        eyev: Tuple,
+        normalv: Tuple,
+        lights: impl IntoIterator<Item = (Tuple, Color)>,
+    ) -> Color {
+        let surface_color = self.color_at(body, point);
+        lights
+            .into_iter()
+            .fold(Color::BLACK(), |acc, (lightv, radiance)| {
+                acc + self.diffuse_and_specular(surface_color, lightv, radiance, eyev, normalv)
+            })
+    }
+}
+
+impl Phong {
+    fn diffuse_and_specular(
+        &self,
+        surface_color: Color,
+        lightv: Tuple,
+        intensity: Color,
+        eyev: Tuple,
+        normalv: Tuple,
+    ) -> Color {
+        let effective_color = surface_color * intensity;
         let light_dot_normal = lightv.dot(&normalv);
         let (diffuse, specular);
 
@@ -156,10 +272,10 @@ impl PhongLighting for Phong {
                 specular = Color::BLACK();
             } else {
                 let factor = reflect_dot_eye.powf(self.shininess as f64);
-                specular = light.intensity * self.specular * factor;
+                specular = intensity * self.specular * factor;
             }
         }
-        ambient + (diffuse + specular) * transparency_factor
+        diffuse + specular
     }
 }
 
@@ -168,8 +284,8 @@ impl Stencil for Phong {
         self.pattern.color_at_in_pattern_space(position)
     }
 
-    fn transform(&self) -> Matrix<4> {
-        self.pattern.transform()
+    fn transformation(&self) -> &Transformation {
+        self.pattern.transformation()
     }
 }
 
@@ -178,3 +294,17 @@ impl From<Phong> for Material {
         Material::Phong(phong)
     }
 }
+
+encode_variants!(Material { 0 => Phong });
+encode_fields!(Phong {
+    pattern,
+    ambient,
+    diffuse,
+    specular,
+    shininess,
+    reflectiveness,
+    transparency,
+    refractive_index,
+    abbe_number,
+    normal_perturbation,
+});
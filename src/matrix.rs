@@ -1,4 +1,4 @@
-use crate::{consts::EPSILON, tuple::Tuple, RoundToNDecimalPlaces};
+use crate::{consts::EPSILON, encoding::Encode, tuple::Tuple, RoundToNDecimalPlaces};
 use std::{
     fmt::{Debug, Display, Formatter},
     ops::{Div, Index, IndexMut, Mul},
@@ -614,6 +614,16 @@ impl<const N: usize> Debug for Matrix<N> {
     }
 }
 
+impl<const N: usize> Encode for Matrix<N> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.inner.encode(out);
+    }
+
+    fn decode(bytes: &mut &[u8]) -> std::io::Result<Self> {
+        Ok(Self::new(<[[f64; N]; N]>::decode(bytes)?))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
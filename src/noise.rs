@@ -0,0 +1,161 @@
+use crate::tuple::Tuple;
+
+// Ken Perlin's reference permutation. Lookups mask the index with 255, so the table wraps
+// instead of being stored twice.
+#[rustfmt::skip]
+const PERMUTATION: [u8; 256] = [
+    151, 160, 137, 91, 90, 15, 131, 13, 201, 95, 96, 53, 194, 233, 7, 225,
+    140, 36, 103, 30, 69, 142, 8, 99, 37, 240, 21, 10, 23, 190, 6, 148,
+    247, 120, 234, 75, 0, 26, 197, 62, 94, 252, 219, 203, 117, 35, 11, 32,
+    57, 177, 33, 88, 237, 149, 56, 87, 174, 20, 125, 136, 171, 168, 68, 175,
+    74, 165, 71, 134, 139, 48, 27, 166, 77, 146, 158, 231, 83, 111, 229, 122,
+    60, 211, 133, 230, 220, 105, 92, 41, 55, 46, 245, 40, 244, 102, 143, 54,
+    65, 25, 63, 161, 1, 216, 80, 73, 209, 76, 132, 187, 208, 89, 18, 169,
+    200, 196, 135, 130, 116, 188, 159, 86, 164, 100, 109, 198, 173, 186, 3, 64,
+    52, 217, 226, 250, 124, 123, 5, 202, 38, 147, 118, 126, 255, 82, 85, 212,
+    207, 206, 59, 227, 47, 16, 58, 17, 182, 189, 28, 42, 223, 183, 170, 213,
+    119, 248, 152, 2, 44, 154, 163, 70, 221, 153, 101, 155, 167, 43, 172, 9,
+    129, 22, 39, 253, 19, 98, 108, 110, 79, 113, 224, 232, 178, 185, 112, 104,
+    218, 246, 97, 228, 251, 34, 242, 193, 238, 210, 144, 12, 191, 179, 162, 241,
+    81, 51, 145, 235, 249, 14, 239, 107, 49, 192, 214, 31, 181, 199, 106, 157,
+    184, 84, 204, 176, 115, 121, 50, 45, 127, 4, 150, 254, 138, 236, 205, 93,
+    222, 114, 67, 29, 24, 72, 243, 141, 128, 195, 78, 66, 215, 61, 156, 180,
+];
+
+fn hash(i: usize) -> usize {
+    PERMUTATION[i & 255] as usize
+}
+
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f64, a: f64, b: f64) -> f64 {
+    a + t * (b - a)
+}
+
+fn gradient(hash: usize, x: f64, y: f64, z: f64) -> f64 {
+    // one of 12 gradient directions towards the edges of a cube
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+/// Improved Perlin noise, roughly in [-1, 1] and zero at every integer lattice point.
+/// ```
+/// use raytracer_rust::noise::perlin;
+/// use raytracer_rust::tuple::Tuple;
+/// assert_eq!(perlin(Tuple::Point(1, 2, 3)), 0.0);
+/// assert_eq!(perlin(Tuple::Point(0.3, 0.7, 1.1)), perlin(Tuple::Point(0.3, 0.7, 1.1)));
+/// assert_ne!(perlin(Tuple::Point(0.3, 0.7, 1.1)), 0.0);
+/// ```
+pub fn perlin(point: Tuple) -> f64 {
+    let (xf, yf, zf) = (point.x.floor(), point.y.floor(), point.z.floor());
+    let (xi, yi, zi) = (
+        (xf as i64 & 255) as usize,
+        (yf as i64 & 255) as usize,
+        (zf as i64 & 255) as usize,
+    );
+    let (x, y, z) = (point.x - xf, point.y - yf, point.z - zf);
+    let (u, v, w) = (fade(x), fade(y), fade(z));
+
+    let a = hash(xi) + yi;
+    let (aa, ab) = (hash(a) + zi, hash(a + 1) + zi);
+    let b = hash(xi + 1) + yi;
+    let (ba, bb) = (hash(b) + zi, hash(b + 1) + zi);
+
+    lerp(
+        w,
+        lerp(
+            v,
+            lerp(
+                u,
+                gradient(hash(aa), x, y, z),
+                gradient(hash(ba), x - 1.0, y, z),
+            ),
+            lerp(
+                u,
+                gradient(hash(ab), x, y - 1.0, z),
+                gradient(hash(bb), x - 1.0, y - 1.0, z),
+            ),
+        ),
+        lerp(
+            v,
+            lerp(
+                u,
+                gradient(hash(aa + 1), x, y, z - 1.0),
+                gradient(hash(ba + 1), x - 1.0, y, z - 1.0),
+            ),
+            lerp(
+                u,
+                gradient(hash(ab + 1), x, y - 1.0, z - 1.0),
+                gradient(hash(bb + 1), x - 1.0, y - 1.0, z - 1.0),
+            ),
+        ),
+    )
+}
+
+/// Sum of ```octaves``` layers of noise, each at twice the frequency and half the
+/// amplitude of the previous one, normalized back into roughly [-1, 1].
+pub fn fractal(point: Tuple, octaves: usize) -> f64 {
+    octave_sum(point, octaves, perlin)
+}
+
+/// Like ```fractal```, but sums the absolute value of each layer, which gives the
+/// creased look of marble veins and flames. The result is roughly in [0, 1].
+pub fn turbulence(point: Tuple, octaves: usize) -> f64 {
+    octave_sum(point, octaves, |p| perlin(p).abs())
+}
+
+fn octave_sum(point: Tuple, octaves: usize, layer: impl Fn(Tuple) -> f64) -> f64 {
+    let (mut sum, mut amplitude, mut frequency, mut total_amplitude) = (0.0, 1.0, 1.0, 0.0);
+    for _ in 0..octaves.max(1) {
+        let scaled = Tuple::Point(
+            point.x * frequency,
+            point.y * frequency,
+            point.z * frequency,
+        );
+        sum += layer(scaled) * amplitude;
+        total_amplitude += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+    sum / total_amplitude
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn noise_stays_in_range_and_varies() {
+        let mut min = f64::INFINITY;
+        let mut max = f64::NEG_INFINITY;
+        for i in 0..1000 {
+            let t = i as f64 * 0.137;
+            let p = Tuple::Point(t, t * 0.73 - 4.0, 10.0 - t * 1.31);
+            for n in [perlin(p), fractal(p, 4)] {
+                min = min.min(n);
+                max = max.max(n);
+            }
+            let turbulent = turbulence(p, 4);
+            assert!((0.0..=1.0).contains(&turbulent));
+        }
+        assert!(min >= -1.0 && max <= 1.0);
+        assert!(min < -0.3 && max > 0.3);
+    }
+
+    #[test]
+    fn noise_is_continuous() {
+        let p = Tuple::Point(1.234, -5.678, 9.1011);
+        let q = Tuple::Point(1.234 + 1e-6, -5.678, 9.1011);
+        assert!((perlin(p) - perlin(q)).abs() < 1e-4);
+    }
+}
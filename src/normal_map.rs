@@ -0,0 +1,254 @@
+use crate::{
+    body::{Body, Intersectable},
+    color::RGB,
+    encoding::{encode_fields, encode_variants},
+    environment_light::luminance,
+    matrix::Matrix,
+    pattern::{Pattern, Stencil},
+    transformation::Transformation,
+    tuple::Tuple,
+    uv_pattern::{ImageTexture, UvMapping, UvStencil},
+};
+
+/// Tilts the shading normal of a material without changing its geometry.
+#[derive(Clone, Debug, PartialEq)]
+pub enum NormalPerturbation {
+    Bump(BumpMap),
+    NormalMap(NormalMap),
+}
+
+/// Treats the luminance of a pattern as a height above the surface, and tilts the normal
+/// down its slope. Noise patterns such as ```Clouds``` make good ripples and dimples.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BumpMap {
+    height: Pattern,
+    strength: f64,
+    delta: f64,
+}
+
+/// Tangent space normal map: red, green and blue of the texture give the normal along
+/// the direction of increasing u, increasing v and straight out of the surface.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NormalMap {
+    texture: ImageTexture,
+    mapping: UvMapping,
+    pub transform: Transformation,
+    strength: f64,
+}
+
+impl NormalPerturbation {
+    /// Perturbs ```normalv```, the outward facing world space normal at ```point```.
+    pub fn perturb(&self, body: &Body, point: Tuple, normalv: Tuple) -> Tuple {
+        match self {
+            NormalPerturbation::Bump(bump) => bump.perturb(body, point, normalv),
+            NormalPerturbation::NormalMap(normal_map) => normal_map.perturb(body, point, normalv),
+        }
+    }
+}
+
+impl BumpMap {
+    pub fn new(height: Pattern, strength: f64) -> Self {
+        Self {
+            height,
+            strength,
+            delta: 0.001,
+        }
+    }
+
+    /// Step used for the finite differences, in world units.
+    pub fn with_delta(mut self, delta: f64) -> Self {
+        self.delta = delta;
+        self
+    }
+
+    fn height_at(&self, body: &Body, point: Tuple) -> f64 {
+        luminance(self.height.color_at(body, point))
+    }
+
+    pub fn perturb(&self, body: &Body, point: Tuple, normalv: Tuple) -> Tuple {
+        let d = self.delta;
+        let slope = |offset: Tuple| {
+            (self.height_at(body, point + offset) - self.height_at(body, point - offset))
+                / (2.0 * d)
+        };
+        let gradient = Tuple::Vector(
+            slope(Tuple::Vector(d, 0.0, 0.0)),
+            slope(Tuple::Vector(0.0, d, 0.0)),
+            slope(Tuple::Vector(0.0, 0.0, d)),
+        );
+        // only the part of the slope along the surface tilts the normal
+        let surface_gradient = gradient - normalv * gradient.dot(&normalv);
+        (normalv - surface_gradient * self.strength).normalize()
+    }
+}
+
+impl NormalMap {
+    pub fn new(texture: ImageTexture, mapping: UvMapping, transform: Matrix<4>) -> Self {
+        Self {
+            texture,
+            mapping,
+            transform: transform.into(),
+            strength: 1.0,
+        }
+    }
+
+    /// Scales how far the map tilts the normal, 0.0 leaving it untouched.
+    pub fn with_strength(mut self, strength: f64) -> Self {
+        self.strength = strength;
+        self
+    }
+
+    fn uv_at(&self, body: &Body, point: Tuple) -> (f64, f64) {
+        let object_point = body.transformation().inverse() * point;
+        self.mapping.map(self.transform.inverse() * object_point)
+    }
+
+    pub fn perturb(&self, body: &Body, point: Tuple, normalv: Tuple) -> Tuple {
+        let (u, v) = self.uv_at(body, point);
+        let (tangent, bitangent) = self.tangent_frame(body, point, normalv, u);
+        let texel = self.texture.color_at_uv(u, v);
+        let x = (2.0 * texel.red() - 1.0) * self.strength;
+        let y = (2.0 * texel.green() - 1.0) * self.strength;
+        let z = 2.0 * texel.blue() - 1.0;
+        (tangent * x + bitangent * y + normalv * z).normalize()
+    }
+
+    /// Directions along the surface in which u increases, and the one perpendicular to
+    /// it in which v increases, found by stepping across the uv mapping.
+    fn tangent_frame(&self, body: &Body, point: Tuple, normalv: Tuple, u: f64) -> (Tuple, Tuple) {
+        let d = 0.0001;
+        let du = |offset: Tuple| {
+            let mut difference = self.uv_at(body, point + offset).0 - u;
+            // don't let the seam where u wraps from 1 back to 0 swamp the slope
+            if difference > 0.5 {
+                difference -= 1.0;
+            } else if difference < -0.5 {
+                difference += 1.0;
+            }
+            difference / d
+        };
+        let gradient = Tuple::Vector(
+            du(Tuple::Vector(d, 0.0, 0.0)),
+            du(Tuple::Vector(0.0, d, 0.0)),
+            du(Tuple::Vector(0.0, 0.0, d)),
+        );
+        let mut tangent = gradient - normalv * gradient.dot(&normalv);
+        if tangent.magnitude() < 1e-9 {
+            // no usable slope, e.g. at a pole: any direction along the surface will do
+            let helper = if normalv.x.abs() < 0.9 {
+                Tuple::Vector(1, 0, 0)
+            } else {
+                Tuple::Vector(0, 1, 0)
+            };
+            tangent = helper - normalv * helper.dot(&normalv);
+        }
+        let tangent = tangent.normalize();
+        (tangent, tangent.cross(&normalv))
+    }
+}
+
+impl From<BumpMap> for NormalPerturbation {
+    fn from(bump: BumpMap) -> Self {
+        NormalPerturbation::Bump(bump)
+    }
+}
+
+impl From<NormalMap> for NormalPerturbation {
+    fn from(normal_map: NormalMap) -> Self {
+        NormalPerturbation::NormalMap(normal_map)
+    }
+}
+
+encode_variants!(NormalPerturbation {
+    0 => Bump,
+    1 => NormalMap,
+});
+encode_fields!(BumpMap {
+    height,
+    strength,
+    delta
+});
+encode_fields!(NormalMap {
+    texture,
+    mapping,
+    transform,
+    strength
+});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        canvas::Canvas,
+        color::Color,
+        pattern::Gradient,
+        plane::Plane,
+        uv_pattern::{TextureFilter, WrapMode},
+    };
+
+    #[test]
+    fn flat_bump_map_keeps_the_normal() {
+        let plane = Body::from(Plane::default());
+        let bump = BumpMap::new(Pattern::default(), 1.0);
+        let normalv = Tuple::Vector(0, 1, 0);
+        assert_eq!(
+            bump.perturb(&plane, Tuple::Point(0.3, 0.0, -0.7), normalv),
+            normalv
+        );
+    }
+
+    #[test]
+    fn bump_map_tilts_the_normal_down_the_slope() {
+        let plane = Body::from(Plane::default());
+        // height rises along x, so the normal should lean towards -x
+        let ramp = Pattern::Gradient(Gradient::new(
+            Color::BLACK(),
+            Color::WHITE(),
+            Matrix::Identity(),
+        ));
+        let bump = BumpMap::new(ramp, 1.0);
+        let perturbed = bump.perturb(&plane, Tuple::Point(0.5, 0.0, 0.0), Tuple::Vector(0, 1, 0));
+        let expected = Tuple::Vector(-1.0, 1.0, 0.0).normalize();
+        assert_eq!(perturbed, expected);
+    }
+
+    #[test]
+    fn normal_map_is_read_in_tangent_space() {
+        let plane = Body::from(Plane::default());
+        let mut image = Canvas::new(1, 1);
+        // (1, 0, 1) in tangent space once remapped from [0, 1]
+        image.set_color_at_pixel(0, 0, Color::new(1.0, 0.5, 1.0));
+        let texture = ImageTexture::new(image, TextureFilter::Nearest, WrapMode::Repeat);
+        let normal_map = NormalMap::new(texture, UvMapping::Planar, Matrix::Identity());
+        let normalv = Tuple::Vector(0, 1, 0);
+        // planar u follows +x on a plane
+        assert_eq!(
+            normal_map.perturb(&plane, Tuple::Point(0.25, 0.0, 0.25), normalv),
+            Tuple::Vector(1.0, 1.0, 0.0).normalize()
+        );
+        let flat = normal_map.with_strength(0.0);
+        assert_eq!(
+            flat.perturb(&plane, Tuple::Point(0.25, 0.0, 0.25), normalv),
+            normalv
+        );
+    }
+
+    #[test]
+    fn normal_map_green_tilts_towards_increasing_v() {
+        let plane = Body::from(Plane::default());
+        let mut image = Canvas::new(1, 1);
+        // (0, 1, 1) in tangent space: leans along v only
+        image.set_color_at_pixel(0, 0, Color::new(0.5, 1.0, 1.0));
+        let texture = ImageTexture::new(image, TextureFilter::Nearest, WrapMode::Repeat);
+        let normal_map = NormalMap::new(texture, UvMapping::Planar, Matrix::Identity());
+        // planar v follows +z on a plane
+        assert_eq!(
+            normal_map.perturb(
+                &plane,
+                Tuple::Point(0.25, 0.0, 0.25),
+                Tuple::Vector(0, 1, 0)
+            ),
+            Tuple::Vector(0.0, 1.0, 1.0).normalize()
+        );
+    }
+}
@@ -1,71 +1,192 @@
 use crate::{
     body::{Body, Intersectable},
     color::Color,
+    encoding::{encode_fields, encode_units, encode_variants, unknown_tag, Encode},
     matrix::Matrix,
+    noise::{fractal, perlin, turbulence},
+    transformation::Transformation,
     tuple::Tuple,
+    uv_pattern::TextureMap,
 };
 
 pub trait Stencil {
     fn color_at(&self, body: &Body, world_point: Tuple) -> Color {
-        let object_point = body.transform().inverse() * world_point;
-        let pattern_point = self.transform().inverse() * object_point;
+        let object_point = body.transformation().inverse() * world_point;
+        let pattern_point = self.transformation().inverse() * object_point;
 
         self.color_at_in_pattern_space(pattern_point)
     }
-    fn transform(&self) -> Matrix<4>;
+    fn transformation(&self) -> &Transformation;
+    fn transform(&self) -> Matrix<4> {
+        self.transformation().matrix()
+    }
     fn color_at_in_pattern_space(&self, position: Tuple) -> Color;
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Pattern {
+    Blended(Blended),
     Checkers(Checkers),
+    Clouds(Clouds),
+    ColorRamp(ColorRamp),
     Flat(Flat),
     Gradient(Gradient),
+    Marble(Marble),
+    Perturbed(Perturbed),
+    RadialGradient(RadialGradient),
     Ring(Ring),
     Striped(Striped),
+    TextureMap(TextureMap),
+    Wood(Wood),
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Striped {
-    color_a: Color,
-    color_b: Color,
-    pub transform: Matrix<4>,
+    a: Box<Pattern>,
+    b: Box<Pattern>,
+    pub transform: Transformation,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub struct Flat {
     color: Color,
-    pub transform: Matrix<4>,
+    pub transform: Transformation,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Gradient {
+    a: Box<Pattern>,
+    b: Box<Pattern>,
+    pub transform: Transformation,
+    mode: GradientMode,
+}
+
+/// Blends outwards from ```a``` at the origin to ```b``` at distance 1.
+/// Repeating, this gives soft rings; clamped, a single glow.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RadialGradient {
+    a: Box<Pattern>,
+    b: Box<Pattern>,
+    pub transform: Transformation,
+    mode: GradientMode,
+}
+
+/// What a gradient does past the end of its unit span.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum GradientMode {
+    /// Starts over at every integer.
+    #[default]
+    Repeat,
+    /// Holds the end colors beyond 0 and 1.
+    Clamp,
+}
+
+/// Colors from a list of ```(position, color)``` stops, looked up by ```driver```.
+/// Positions before the first or after the last stop take that stop's color.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColorRamp {
+    stops: Vec<(f64, Color)>,
+    interpolation: Interpolation,
+    driver: RampDriver,
+    pub transform: Transformation,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Interpolation {
+    #[default]
+    Linear,
+    Smoothstep,
+}
+
+/// Which value of the pattern space point selects the position on a ```ColorRamp```.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum RampDriver {
+    #[default]
+    X,
+    /// Distance from the origin.
+    Distance,
+    /// Fractal noise with the given number of octaves, remapped to [0, 1].
+    Noise(usize),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Checkers {
+    a: Box<Pattern>,
+    b: Box<Pattern>,
+    pub transform: Transformation,
+    is_three_dimensional: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ring {
+    a: Box<Pattern>,
+    b: Box<Pattern>,
+    pub transform: Transformation,
+}
+
+/// Mixes two patterns, ```weight``` 0.0 being all ```a``` and 1.0 all ```b```.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Blended {
+    a: Box<Pattern>,
+    b: Box<Pattern>,
+    weight: f64,
+    pub transform: Transformation,
+}
+
+/// Veins of ```color_b``` running through ```color_a``` along the x axis,
+/// bent by turbulent noise.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Marble {
     color_a: Color,
     color_b: Color,
-    pub transform: Matrix<4>,
+    pub transform: Transformation,
+    octaves: usize,
+    turbulence: f64,
 }
 
+/// Concentric rings around the y axis, alternating smoothly between the two colors,
+/// with their radius wobbled by noise.
 #[derive(Clone, Copy, Debug, PartialEq)]
-pub struct Checkers {
+pub struct Wood {
     color_a: Color,
     color_b: Color,
-    pub transform: Matrix<4>,
-    is_three_dimensional: bool,
+    pub transform: Transformation,
+    octaves: usize,
+    turbulence: f64,
 }
 
+/// Fractal noise blending between ```color_a``` and ```color_b```.
 #[derive(Clone, Copy, Debug, PartialEq)]
-pub struct Ring {
+pub struct Clouds {
     color_a: Color,
     color_b: Color,
-    pub transform: Matrix<4>,
+    pub transform: Transformation,
+    octaves: usize,
+}
+
+/// Jitters the pattern space point by up to ```scale``` before handing it to ```pattern```.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Perturbed {
+    pattern: Box<Pattern>,
+    pub transform: Transformation,
+    scale: f64,
+    octaves: usize,
 }
 
 impl Striped {
     pub fn new(color_a: Color, color_b: Color, transform: Matrix<4>) -> Self {
-        Self {
-            color_a,
-            color_b,
+        Self::nested(
+            Pattern::Flat(Flat::new(color_a)),
+            Pattern::Flat(Flat::new(color_b)),
             transform,
+        )
+    }
+
+    pub fn nested(a: Pattern, b: Pattern, transform: Matrix<4>) -> Self {
+        Self {
+            a: Box::new(a),
+            b: Box::new(b),
+            transform: transform.into(),
         }
     }
 }
@@ -74,27 +195,136 @@ impl Flat {
     pub fn new(color: Color) -> Self {
         Self {
             color,
-            transform: Matrix::Identity(),
+            transform: Transformation::default(),
         }
     }
 }
 
 impl Gradient {
     pub fn new(color_a: Color, color_b: Color, transform: Matrix<4>) -> Self {
+        Self::nested(
+            Pattern::Flat(Flat::new(color_a)),
+            Pattern::Flat(Flat::new(color_b)),
+            transform,
+        )
+    }
+
+    pub fn nested(a: Pattern, b: Pattern, transform: Matrix<4>) -> Self {
         Self {
-            color_a,
-            color_b,
+            a: Box::new(a),
+            b: Box::new(b),
+            transform: transform.into(),
+            mode: GradientMode::default(),
+        }
+    }
+
+    pub fn with_mode(mut self, mode: GradientMode) -> Self {
+        self.mode = mode;
+        self
+    }
+}
+
+impl RadialGradient {
+    pub fn new(color_a: Color, color_b: Color, transform: Matrix<4>) -> Self {
+        Self::nested(
+            Pattern::Flat(Flat::new(color_a)),
+            Pattern::Flat(Flat::new(color_b)),
             transform,
+        )
+    }
+
+    pub fn nested(a: Pattern, b: Pattern, transform: Matrix<4>) -> Self {
+        Self {
+            a: Box::new(a),
+            b: Box::new(b),
+            transform: transform.into(),
+            mode: GradientMode::default(),
+        }
+    }
+
+    pub fn with_mode(mut self, mode: GradientMode) -> Self {
+        self.mode = mode;
+        self
+    }
+}
+
+impl GradientMode {
+    fn apply(&self, t: f64) -> f64 {
+        match self {
+            GradientMode::Repeat => t - t.floor(),
+            GradientMode::Clamp => t.clamp(0.0, 1.0),
+        }
+    }
+}
+
+impl ColorRamp {
+    /// Stops don't need to be sorted. Panics if there are none.
+    pub fn new(mut stops: Vec<(f64, Color)>, transform: Matrix<4>) -> Self {
+        assert!(!stops.is_empty(), "a color ramp needs at least one stop");
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self {
+            stops,
+            interpolation: Interpolation::default(),
+            driver: RampDriver::default(),
+            transform: transform.into(),
         }
     }
+
+    pub fn with_interpolation(mut self, interpolation: Interpolation) -> Self {
+        self.interpolation = interpolation;
+        self
+    }
+
+    pub fn with_driver(mut self, driver: RampDriver) -> Self {
+        self.driver = driver;
+        self
+    }
+
+    /// ```
+    /// use raytracer_rust::color::Color;
+    /// use raytracer_rust::matrix::Matrix;
+    /// use raytracer_rust::pattern::ColorRamp;
+    /// let ramp = ColorRamp::new(
+    ///     vec![(1.0, Color::WHITE()), (0.0, Color::BLACK()), (0.5, Color::RED())],
+    ///     Matrix::Identity(),
+    /// );
+    /// assert_eq!(ramp.color_at_position(-1.0), Color::BLACK());
+    /// assert_eq!(ramp.color_at_position(0.25), Color::new(0.5, 0.0, 0.0));
+    /// assert_eq!(ramp.color_at_position(0.75), Color::new(1.0, 0.5, 0.5));
+    /// assert_eq!(ramp.color_at_position(2.0), Color::WHITE());
+    /// ```
+    pub fn color_at_position(&self, position: f64) -> Color {
+        let next = self.stops.partition_point(|(at, _)| *at <= position);
+        if next == 0 {
+            return self.stops[0].1;
+        }
+        if next == self.stops.len() {
+            return self.stops[next - 1].1;
+        }
+        let (from, color_from) = self.stops[next - 1];
+        let (to, color_to) = self.stops[next];
+        let mut t = (position - from) / (to - from);
+        if self.interpolation == Interpolation::Smoothstep {
+            t = t * t * (3.0 - 2.0 * t);
+        }
+        color_from + (color_to - color_from) * t
+    }
 }
 
 impl Ring {
     pub fn new(color_a: Color, color_b: Color, transform: Matrix<4>) -> Self {
-        Self {
-            color_a,
-            color_b,
+        Self::nested(
+            Pattern::Flat(Flat::new(color_a)),
+            Pattern::Flat(Flat::new(color_b)),
             transform,
+        )
+    }
+
+    pub fn nested(a: Pattern, b: Pattern, transform: Matrix<4>) -> Self {
+        Self {
+            a: Box::new(a),
+            b: Box::new(b),
+            transform: transform.into(),
         }
     }
 }
@@ -106,32 +336,164 @@ impl Checkers {
         transform: Matrix<4>,
         is_three_dimensional: bool,
     ) -> Self {
+        Self::nested(
+            Pattern::Flat(Flat::new(color_a)),
+            Pattern::Flat(Flat::new(color_b)),
+            transform,
+            is_three_dimensional,
+        )
+    }
+
+    pub fn nested(
+        a: Pattern,
+        b: Pattern,
+        transform: Matrix<4>,
+        is_three_dimensional: bool,
+    ) -> Self {
+        Self {
+            a: Box::new(a),
+            b: Box::new(b),
+            transform: transform.into(),
+            is_three_dimensional,
+        }
+    }
+}
+
+impl Blended {
+    pub fn new(a: Pattern, b: Pattern, weight: f64) -> Self {
+        Self {
+            a: Box::new(a),
+            b: Box::new(b),
+            weight,
+            transform: Transformation::default(),
+        }
+    }
+
+    /// Blends both patterns equally.
+    pub fn average(a: Pattern, b: Pattern) -> Self {
+        Self::new(a, b, 0.5)
+    }
+
+    pub fn with_transform(mut self, transform: Matrix<4>) -> Self {
+        self.transform.set(transform);
+        self
+    }
+}
+
+impl Marble {
+    pub fn new(color_a: Color, color_b: Color, transform: Matrix<4>) -> Self {
         Self {
             color_a,
             color_b,
-            transform,
-            is_three_dimensional,
+            transform: transform.into(),
+            octaves: 4,
+            turbulence: 4.0,
         }
     }
+
+    pub fn with_octaves(mut self, octaves: usize) -> Self {
+        self.octaves = octaves;
+        self
+    }
+
+    pub fn with_turbulence(mut self, turbulence: f64) -> Self {
+        self.turbulence = turbulence;
+        self
+    }
+}
+
+impl Wood {
+    pub fn new(color_a: Color, color_b: Color, transform: Matrix<4>) -> Self {
+        Self {
+            color_a,
+            color_b,
+            transform: transform.into(),
+            octaves: 2,
+            turbulence: 0.3,
+        }
+    }
+
+    pub fn with_octaves(mut self, octaves: usize) -> Self {
+        self.octaves = octaves;
+        self
+    }
+
+    pub fn with_turbulence(mut self, turbulence: f64) -> Self {
+        self.turbulence = turbulence;
+        self
+    }
+}
+
+impl Clouds {
+    pub fn new(color_a: Color, color_b: Color, transform: Matrix<4>) -> Self {
+        Self {
+            color_a,
+            color_b,
+            transform: transform.into(),
+            octaves: 6,
+        }
+    }
+
+    pub fn with_octaves(mut self, octaves: usize) -> Self {
+        self.octaves = octaves;
+        self
+    }
+}
+
+impl Perturbed {
+    pub fn new(pattern: Pattern, scale: f64) -> Self {
+        Self {
+            pattern: Box::new(pattern),
+            transform: Transformation::default(),
+            scale,
+            octaves: 1,
+        }
+    }
+
+    pub fn with_octaves(mut self, octaves: usize) -> Self {
+        self.octaves = octaves;
+        self
+    }
+
+    pub fn with_transform(mut self, transform: Matrix<4>) -> Self {
+        self.transform.set(transform);
+        self
+    }
 }
 
 impl Stencil for Pattern {
     fn color_at_in_pattern_space(&self, position: Tuple) -> Color {
         match self {
+            Pattern::Blended(blended) => blended.color_at_in_pattern_space(position),
             Pattern::Flat(flat) => flat.color_at_in_pattern_space(position),
             Pattern::Striped(stripe) => stripe.color_at_in_pattern_space(position),
             Pattern::Gradient(gradient) => gradient.color_at_in_pattern_space(position),
             Pattern::Ring(ring) => ring.color_at_in_pattern_space(position),
             Pattern::Checkers(checkers) => checkers.color_at_in_pattern_space(position),
+            Pattern::TextureMap(texture_map) => texture_map.color_at_in_pattern_space(position),
+            Pattern::Marble(marble) => marble.color_at_in_pattern_space(position),
+            Pattern::Wood(wood) => wood.color_at_in_pattern_space(position),
+            Pattern::Clouds(clouds) => clouds.color_at_in_pattern_space(position),
+            Pattern::RadialGradient(radial) => radial.color_at_in_pattern_space(position),
+            Pattern::ColorRamp(ramp) => ramp.color_at_in_pattern_space(position),
+            Pattern::Perturbed(perturbed) => perturbed.color_at_in_pattern_space(position),
         }
     }
-    fn transform(&self) -> Matrix<4> {
+    fn transformation(&self) -> &Transformation {
         match self {
-            Pattern::Flat(flat) => flat.transform(),
-            Pattern::Striped(stripe) => stripe.transform(),
-            Pattern::Gradient(gradient) => gradient.transform(),
-            Pattern::Ring(ring) => ring.transform(),
-            Pattern::Checkers(checkers) => checkers.transform(),
+            Pattern::Blended(blended) => blended.transformation(),
+            Pattern::Flat(flat) => flat.transformation(),
+            Pattern::Striped(stripe) => stripe.transformation(),
+            Pattern::Gradient(gradient) => gradient.transformation(),
+            Pattern::Ring(ring) => ring.transformation(),
+            Pattern::Checkers(checkers) => checkers.transformation(),
+            Pattern::TextureMap(texture_map) => texture_map.transformation(),
+            Pattern::Marble(marble) => marble.transformation(),
+            Pattern::Wood(wood) => wood.transformation(),
+            Pattern::Clouds(clouds) => clouds.transformation(),
+            Pattern::RadialGradient(radial) => radial.transformation(),
+            Pattern::ColorRamp(ramp) => ramp.transformation(),
+            Pattern::Perturbed(perturbed) => perturbed.transformation(),
         }
     }
 }
@@ -141,30 +503,65 @@ impl Stencil for Flat {
         self.color
     }
 
-    fn transform(&self) -> Matrix<4> {
-        self.transform
+    fn transformation(&self) -> &Transformation {
+        &self.transform
     }
 }
 
+/// Evaluates a sub-pattern at a point given in its parent's pattern space.
+fn sub_color(pattern: &Pattern, position: Tuple) -> Color {
+    pattern.color_at_in_pattern_space(pattern.transformation().inverse() * position)
+}
+
 impl Stencil for Striped {
     fn color_at_in_pattern_space(&self, position: Tuple) -> Color {
         if position.x.floor() as isize % 2 == 0 {
-            self.color_a
+            sub_color(&self.a, position)
         } else {
-            self.color_b
+            sub_color(&self.b, position)
         }
     }
-    fn transform(&self) -> Matrix<4> {
-        self.transform
+    fn transformation(&self) -> &Transformation {
+        &self.transform
     }
 }
 
 impl Stencil for Gradient {
     fn color_at_in_pattern_space(&self, position: Tuple) -> Color {
-        self.color_a + (self.color_b - self.color_a) * (position.x - position.x.floor())
+        let color_a = sub_color(&self.a, position);
+        let color_b = sub_color(&self.b, position);
+        color_a + (color_b - color_a) * self.mode.apply(position.x)
     }
-    fn transform(&self) -> Matrix<4> {
-        self.transform
+    fn transformation(&self) -> &Transformation {
+        &self.transform
+    }
+}
+
+impl Stencil for RadialGradient {
+    fn color_at_in_pattern_space(&self, position: Tuple) -> Color {
+        let distance = (position.x.powi(2) + position.y.powi(2) + position.z.powi(2)).sqrt();
+        let color_a = sub_color(&self.a, position);
+        let color_b = sub_color(&self.b, position);
+        color_a + (color_b - color_a) * self.mode.apply(distance)
+    }
+    fn transformation(&self) -> &Transformation {
+        &self.transform
+    }
+}
+
+impl Stencil for ColorRamp {
+    fn color_at_in_pattern_space(&self, position: Tuple) -> Color {
+        let at = match self.driver {
+            RampDriver::X => position.x,
+            RampDriver::Distance => {
+                (position.x.powi(2) + position.y.powi(2) + position.z.powi(2)).sqrt()
+            }
+            RampDriver::Noise(octaves) => 0.5 + 0.5 * fractal(position, octaves),
+        };
+        self.color_at_position(at)
+    }
+    fn transformation(&self) -> &Transformation {
+        &self.transform
     }
 }
 
@@ -172,13 +569,13 @@ impl Stencil for Ring {
     fn color_at_in_pattern_space(&self, position: Tuple) -> Color {
         let distance_from_center = (position.x.powi(2) + position.y.powi(2)).sqrt();
         if distance_from_center.floor() as isize % 2 == 0 {
-            self.color_a
+            sub_color(&self.a, position)
         } else {
-            self.color_b
+            sub_color(&self.b, position)
         }
     }
-    fn transform(&self) -> Matrix<4> {
-        self.transform
+    fn transformation(&self) -> &Transformation {
+        &self.transform
     }
 }
 
@@ -188,13 +585,81 @@ impl Stencil for Checkers {
             + position.z.floor()
             + (self.is_three_dimensional as u8 as f64) * position.y.floor();
         if distance as isize % 2 == 0 {
-            self.color_a
+            sub_color(&self.a, position)
         } else {
-            self.color_b
+            sub_color(&self.b, position)
         }
     }
-    fn transform(&self) -> Matrix<4> {
-        self.transform
+    fn transformation(&self) -> &Transformation {
+        &self.transform
+    }
+}
+
+impl Stencil for Blended {
+    fn color_at_in_pattern_space(&self, position: Tuple) -> Color {
+        sub_color(&self.a, position) * (1.0 - self.weight)
+            + sub_color(&self.b, position) * self.weight
+    }
+    fn transformation(&self) -> &Transformation {
+        &self.transform
+    }
+}
+
+impl Stencil for Marble {
+    fn color_at_in_pattern_space(&self, position: Tuple) -> Color {
+        let phase = position.x + self.turbulence * turbulence(position, self.octaves);
+        let t = 0.5 + 0.5 * phase.sin();
+        self.color_a + (self.color_b - self.color_a) * t
+    }
+    fn transformation(&self) -> &Transformation {
+        &self.transform
+    }
+}
+
+impl Stencil for Wood {
+    fn color_at_in_pattern_space(&self, position: Tuple) -> Color {
+        let distance_from_center = (position.x.powi(2) + position.z.powi(2)).sqrt()
+            + self.turbulence * fractal(position, self.octaves);
+        let ring = distance_from_center - distance_from_center.floor();
+        // rise and fall within each ring so neighbouring rings meet without a seam
+        let t = 1.0 - (2.0 * ring - 1.0).abs();
+        self.color_a + (self.color_b - self.color_a) * t
+    }
+    fn transformation(&self) -> &Transformation {
+        &self.transform
+    }
+}
+
+impl Stencil for Clouds {
+    fn color_at_in_pattern_space(&self, position: Tuple) -> Color {
+        let t = (0.5 + 0.5 * fractal(position, self.octaves)).clamp(0.0, 1.0);
+        self.color_a + (self.color_b - self.color_a) * t
+    }
+    fn transformation(&self) -> &Transformation {
+        &self.transform
+    }
+}
+
+impl Stencil for Perturbed {
+    fn color_at_in_pattern_space(&self, position: Tuple) -> Color {
+        // offset the lookups so each axis gets its own, uncorrelated noise
+        let offset = |dx: f64, dy: f64, dz: f64| {
+            let shifted = Tuple::Point(position.x + dx, position.y + dy, position.z + dz);
+            if self.octaves > 1 {
+                fractal(shifted, self.octaves)
+            } else {
+                perlin(shifted)
+            }
+        };
+        let jittered = Tuple::Point(
+            position.x + self.scale * offset(0.0, 0.0, 0.0),
+            position.y + self.scale * offset(31.7, 5.3, 17.1),
+            position.z + self.scale * offset(-12.9, 43.1, 8.7),
+        );
+        sub_color(&self.pattern, jittered)
+    }
+    fn transformation(&self) -> &Transformation {
+        &self.transform
     }
 }
 
@@ -204,28 +669,114 @@ impl Default for Pattern {
     }
 }
 
-impl Default for Flat {
-    fn default() -> Self {
-        Self {
-            color: Color::default(),
-            transform: Matrix::Identity(),
+encode_variants!(Pattern {
+    0 => Blended,
+    1 => Checkers,
+    2 => Clouds,
+    3 => ColorRamp,
+    4 => Flat,
+    5 => Gradient,
+    6 => Marble,
+    7 => Perturbed,
+    8 => RadialGradient,
+    9 => Ring,
+    10 => Striped,
+    11 => TextureMap,
+    12 => Wood,
+});
+encode_fields!(Blended {
+    a,
+    b,
+    weight,
+    transform
+});
+encode_fields!(Checkers {
+    a,
+    b,
+    transform,
+    is_three_dimensional
+});
+encode_fields!(Clouds {
+    color_a,
+    color_b,
+    transform,
+    octaves
+});
+encode_fields!(ColorRamp {
+    stops,
+    interpolation,
+    driver,
+    transform
+});
+encode_fields!(Flat { color, transform });
+encode_fields!(Gradient {
+    a,
+    b,
+    transform,
+    mode
+});
+encode_fields!(Marble {
+    color_a,
+    color_b,
+    transform,
+    octaves,
+    turbulence
+});
+encode_fields!(Perturbed {
+    pattern,
+    transform,
+    scale,
+    octaves
+});
+encode_fields!(RadialGradient {
+    a,
+    b,
+    transform,
+    mode
+});
+encode_fields!(Ring { a, b, transform });
+encode_fields!(Striped { a, b, transform });
+encode_fields!(Wood {
+    color_a,
+    color_b,
+    transform,
+    octaves,
+    turbulence
+});
+encode_units!(GradientMode { 0 => Repeat, 1 => Clamp });
+encode_units!(Interpolation { 0 => Linear, 1 => Smoothstep });
+
+impl Encode for RampDriver {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            RampDriver::X => out.push(0),
+            RampDriver::Distance => out.push(1),
+            RampDriver::Noise(octaves) => {
+                out.push(2);
+                octaves.encode(out);
+            }
+        }
+    }
+
+    fn decode(bytes: &mut &[u8]) -> std::io::Result<Self> {
+        match u8::decode(bytes)? {
+            0 => Ok(RampDriver::X),
+            1 => Ok(RampDriver::Distance),
+            2 => Ok(RampDriver::Noise(usize::decode(bytes)?)),
+            tag => Err(unknown_tag("RampDriver", tag)),
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::sphere::Sphere;
+    use crate::{color::RGB, sphere::Sphere};
 
     use super::*;
 
     #[test]
     fn stripe_works() {
-        let stripe = Striped {
-            color_a: Color::WHITE(),
-            color_b: Color::BLACK(),
-            transform: Matrix::Identity(),
-        };
+        let stripe = Striped::new(Color::WHITE(), Color::BLACK(), Matrix::Identity());
         assert_eq!(
             stripe.color_at_in_pattern_space(Tuple::Point(0.0, 0.0, 0.0)),
             Color::WHITE()
@@ -281,11 +832,11 @@ mod tests {
     #[test]
     fn striped_pattern_adheres_to_object_transform() {
         let transform = Matrix::Scaling(2.0, 2.0, 2.0);
-        let pattern = Pattern::Striped(Striped {
-            color_a: Color::BLACK(),
-            color_b: Color::WHITE(),
-            transform: Matrix::Identity(),
-        });
+        let pattern = Pattern::Striped(Striped::new(
+            Color::BLACK(),
+            Color::WHITE(),
+            Matrix::Identity(),
+        ));
         let body = Body::from(Sphere::default().with_transform(transform));
 
         assert_eq!(
@@ -293,4 +844,190 @@ mod tests {
             pattern.color_at(&body, Tuple::Point(1.5, 0.0, 0.0),)
         );
     }
+
+    #[test]
+    fn perturbing_by_nothing_keeps_the_inner_pattern() {
+        let inner = Pattern::Striped(Striped::new(
+            Color::WHITE(),
+            Color::BLACK(),
+            Matrix::Scaling(0.5, 1.0, 1.0),
+        ));
+        let perturbed = Perturbed::new(inner.clone(), 0.0);
+        for x in [-1.3, -0.2, 0.3, 0.7, 1.9] {
+            let point = Tuple::Point(x, 0.4, -2.2);
+            assert_eq!(
+                perturbed.color_at_in_pattern_space(point),
+                inner.color_at_in_pattern_space(inner.transformation().inverse() * point)
+            );
+        }
+    }
+
+    #[test]
+    fn perturbing_moves_straight_stripe_edges() {
+        let inner = Pattern::Striped(Striped::new(
+            Color::WHITE(),
+            Color::BLACK(),
+            Matrix::Identity(),
+        ));
+        let perturbed = Perturbed::new(inner.clone(), 0.5);
+        // along a line parallel to the stripes the plain pattern never changes
+        let changes = (0..200)
+            .map(|i| Tuple::Point(0.9, 0.0, i as f64 * 0.05))
+            .filter(|point| {
+                perturbed.color_at_in_pattern_space(*point)
+                    != inner.color_at_in_pattern_space(*point)
+            })
+            .count();
+        assert!(changes > 0);
+    }
+
+    #[test]
+    fn noise_patterns_stay_between_their_colors() {
+        let patterns = [
+            Pattern::Marble(Marble::new(
+                Color::WHITE(),
+                Color::BLACK(),
+                Matrix::Identity(),
+            )),
+            Pattern::Wood(Wood::new(
+                Color::WHITE(),
+                Color::BLACK(),
+                Matrix::Identity(),
+            )),
+            Pattern::Clouds(Clouds::new(
+                Color::WHITE(),
+                Color::BLACK(),
+                Matrix::Identity(),
+            )),
+        ];
+        for pattern in patterns {
+            let shades = (0..100)
+                .map(|i| {
+                    let t = i as f64 * 0.173;
+                    pattern
+                        .color_at_in_pattern_space(Tuple::Point(t, 0.3 * t, -t))
+                        .red()
+                })
+                .collect::<Vec<_>>();
+            assert!(shades.iter().all(|shade| (0.0..=1.0).contains(shade)));
+            assert!(shades.iter().any(|shade| *shade > 0.6));
+            assert!(shades.iter().any(|shade| *shade < 0.4));
+        }
+    }
+
+    #[test]
+    fn stripes_can_hold_other_patterns() {
+        let checkers = Pattern::Checkers(Checkers::new(
+            Color::RED(),
+            Color::BLUE(),
+            Matrix::Scaling(0.25, 0.25, 0.25),
+            false,
+        ));
+        let stripe = Striped::nested(
+            checkers,
+            Pattern::Flat(Flat::new(Color::WHITE())),
+            Matrix::Identity(),
+        );
+        // first stripe is checkered in quarter units, second one plain white
+        assert_eq!(
+            stripe.color_at_in_pattern_space(Tuple::Point(0.1, 0.0, 0.1)),
+            Color::RED()
+        );
+        assert_eq!(
+            stripe.color_at_in_pattern_space(Tuple::Point(0.3, 0.0, 0.1)),
+            Color::BLUE()
+        );
+        assert_eq!(
+            stripe.color_at_in_pattern_space(Tuple::Point(1.3, 0.0, 0.1)),
+            Color::WHITE()
+        );
+    }
+
+    #[test]
+    fn blended_mixes_by_weight() {
+        let horizontal = Pattern::Striped(Striped::new(
+            Color::WHITE(),
+            Color::BLACK(),
+            Matrix::Identity(),
+        ));
+        let vertical = Pattern::Striped(Striped::new(
+            Color::WHITE(),
+            Color::BLACK(),
+            Matrix::rotation_Y(crate::consts::PI_BY_2),
+        ));
+        let average = Blended::average(horizontal.clone(), vertical.clone());
+        assert_eq!(
+            average.color_at_in_pattern_space(Tuple::Point(0.5, 0.0, -0.5)),
+            Color::WHITE()
+        );
+        assert_eq!(
+            average.color_at_in_pattern_space(Tuple::Point(1.5, 0.0, -0.5)),
+            Color::new(0.5, 0.5, 0.5)
+        );
+        let mostly_vertical = Blended::new(horizontal, vertical, 0.75);
+        assert_eq!(
+            mostly_vertical.color_at_in_pattern_space(Tuple::Point(1.5, 0.0, -0.5)),
+            Color::new(0.75, 0.75, 0.75)
+        );
+    }
+
+    #[test]
+    fn clamped_gradient_holds_its_end_colors() {
+        let repeating = Gradient::new(Color::WHITE(), Color::BLACK(), Matrix::Identity());
+        let clamped = repeating.clone().with_mode(GradientMode::Clamp);
+        let beyond = Tuple::Point(1.25, 0.0, 0.0);
+        assert_eq!(
+            repeating.color_at_in_pattern_space(beyond),
+            Color::new(0.75, 0.75, 0.75)
+        );
+        assert_eq!(clamped.color_at_in_pattern_space(beyond), Color::BLACK());
+        assert_eq!(
+            clamped.color_at_in_pattern_space(Tuple::Point(-3.0, 0.0, 0.0)),
+            Color::WHITE()
+        );
+    }
+
+    #[test]
+    fn radial_gradient_depends_on_distance() {
+        let radial = RadialGradient::new(Color::WHITE(), Color::BLACK(), Matrix::Identity());
+        assert_eq!(
+            radial.color_at_in_pattern_space(Tuple::Point(0.0, 0.0, 0.5)),
+            Color::new(0.5, 0.5, 0.5)
+        );
+        assert_eq!(
+            radial.color_at_in_pattern_space(Tuple::Point(0.0, 0.3, 0.4)),
+            Color::new(0.5, 0.5, 0.5)
+        );
+        // repeating rings start over past a distance of one
+        assert_eq!(
+            radial.color_at_in_pattern_space(Tuple::Point(1.25, 0.0, 0.0)),
+            Color::new(0.75, 0.75, 0.75)
+        );
+    }
+
+    #[test]
+    fn color_ramp_interpolates_between_stops() {
+        let ramp = ColorRamp::new(
+            vec![(0.0, Color::BLACK()), (2.0, Color::WHITE())],
+            Matrix::Identity(),
+        );
+        assert_eq!(
+            ramp.color_at_in_pattern_space(Tuple::Point(0.5, 9.0, 9.0)),
+            Color::new(0.25, 0.25, 0.25)
+        );
+        let smooth = ramp.clone().with_interpolation(Interpolation::Smoothstep);
+        assert_eq!(
+            smooth.color_at_in_pattern_space(Tuple::Point(0.5, 9.0, 9.0)),
+            Color::new(0.15625, 0.15625, 0.15625)
+        );
+        assert_eq!(
+            smooth.color_at_in_pattern_space(Tuple::Point(1.0, 0.0, 0.0)),
+            Color::new(0.5, 0.5, 0.5)
+        );
+        let by_distance = ramp.with_driver(RampDriver::Distance);
+        assert_eq!(
+            by_distance.color_at_in_pattern_space(Tuple::Point(0.0, 0.6, 0.8)),
+            Color::new(0.5, 0.5, 0.5)
+        );
+    }
 }
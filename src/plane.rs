@@ -1,15 +1,17 @@
 use crate::{
-    body::{Body, Intersectable, IntoBody},
+    body::{Body, Hits, Intersectable, IntoBody},
     consts::EPSILON,
+    encoding::encode_fields,
     material::{Material, Phong},
     matrix::Matrix,
     ray::Ray,
+    transformation::Transformation,
     tuple::Tuple,
 };
 
 #[derive(Clone, Debug)]
 pub struct Plane {
-    transform: Matrix<4>,
+    transform: Transformation,
     material: Material,
 }
 
@@ -22,7 +24,7 @@ impl PartialEq for Plane {
 impl Plane {
     pub fn new(transform: Matrix<4>, material: Material) -> Self {
         Self {
-            transform,
+            transform: transform.into(),
             material,
         }
     }
@@ -42,19 +44,19 @@ impl Intersectable for Plane {
         &mut self.material
     }
 
-    fn transform(&self) -> Matrix<4_usize> {
-        self.transform
+    fn transformation(&self) -> &Transformation {
+        &self.transform
     }
 
-    fn transform_mut(self: &mut Plane) -> &mut Matrix<4_usize> {
+    fn transform_mut(&mut self) -> &mut Transformation {
         &mut self.transform
     }
 
-    fn intersect_in_object_space(&self, object_space_ray: &Ray) -> Vec<f64> {
+    fn intersect_in_object_space(&self, object_space_ray: &Ray) -> Hits {
         if object_space_ray.direction.y.abs() < EPSILON {
-            vec![]
+            Hits::new()
         } else {
-            vec![-object_space_ray.origin.y / object_space_ray.direction.y]
+            Hits::from([-object_space_ray.origin.y / object_space_ray.direction.y])
         }
     }
 
@@ -85,7 +87,12 @@ impl Default for Plane {
     fn default() -> Self {
         Self {
             material: Phong::default().into(),
-            transform: Matrix::Identity(),
+            transform: Transformation::default(),
         }
     }
 }
+
+encode_fields!(Plane {
+    transform,
+    material
+});
@@ -1,4 +1,4 @@
-use crate::{color::Color, tuple::Tuple};
+use crate::{color::Color, encoding::encode_fields, tuple::Tuple};
 
 #[derive(Debug, Clone, Copy)]
 pub struct PointLight {
@@ -15,3 +15,8 @@ impl PointLight {
         }
     }
 }
+
+encode_fields!(PointLight {
+    position,
+    intensity
+});
@@ -0,0 +1,327 @@
+use crate::{
+    body::{Body, Hits, Intersectable, IntoBody},
+    bounds::BoundingBox,
+    consts::EPSILON,
+    encoding::encode_fields,
+    material::{Material, Phong},
+    matrix::Matrix,
+    ray::Ray,
+    solver::solve_quadratic,
+    transformation::Transformation,
+    tuple::Tuple,
+};
+
+/// Surface where ```A·x² + B·y² + C·z² + D·xy + E·xz + F·yz + G·x + H·y + I·z + J = 0```,
+/// with the coefficients stored in that order. The surface is left open where it is
+/// clipped, nothing caps it.
+#[derive(Clone, Debug)]
+pub struct Quadric {
+    pub transform: Transformation,
+    pub material: Material,
+    pub coefficients: [f64; 10],
+    /// Only the part of the surface inside this box (in object space) is kept.
+    pub clip: Option<BoundingBox>,
+}
+
+impl PartialEq for Quadric {
+    fn eq(&self, other: &Self) -> bool {
+        self.transform == other.transform
+            && self.material == other.material
+            && self.coefficients == other.coefficients
+            && self.clip == other.clip
+    }
+}
+
+impl Quadric {
+    pub fn new(transform: Matrix<4>, material: Material, coefficients: [f64; 10]) -> Self {
+        Self {
+            transform: transform.into(),
+            material,
+            coefficients,
+            clip: None,
+        }
+    }
+
+    fn from_coefficients(coefficients: [f64; 10]) -> Self {
+        Self::new(
+            Matrix::Identity(),
+            Material::Phong(Phong::default()),
+            coefficients,
+        )
+    }
+
+    /// ```x²/a² + y²/b² + z²/c² = 1```
+    pub fn ellipsoid(a: f64, b: f64, c: f64) -> Self {
+        Self::from_coefficients([
+            a.powi(-2),
+            b.powi(-2),
+            c.powi(-2),
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            -1.0,
+        ])
+    }
+
+    /// Bowl opening up the y axis, ```y = x²/a² + z²/b²```.
+    pub fn paraboloid(a: f64, b: f64) -> Self {
+        Self::from_coefficients([
+            a.powi(-2),
+            0.0,
+            b.powi(-2),
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            -1.0,
+            0.0,
+            0.0,
+        ])
+    }
+
+    /// Saddle, ```y = x²/a² - z²/b²```.
+    pub fn hyperbolic_paraboloid(a: f64, b: f64) -> Self {
+        Self::from_coefficients([
+            a.powi(-2),
+            0.0,
+            -b.powi(-2),
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            -1.0,
+            0.0,
+            0.0,
+        ])
+    }
+
+    /// Cooling tower around the y axis, ```x²/a² + z²/a² - y²/b² = 1```.
+    pub fn hyperboloid_of_one_sheet(a: f64, b: f64) -> Self {
+        Self::from_coefficients([
+            a.powi(-2),
+            -b.powi(-2),
+            a.powi(-2),
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            -1.0,
+        ])
+    }
+
+    /// Two bowls facing away from each other along the y axis, ```y²/b² - x²/a² - z²/a² = 1```.
+    pub fn hyperboloid_of_two_sheets(a: f64, b: f64) -> Self {
+        Self::from_coefficients([
+            -a.powi(-2),
+            b.powi(-2),
+            -a.powi(-2),
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            -1.0,
+        ])
+    }
+
+    /// Double cone around the y axis, ```x²/a² + z²/b² = y²```.
+    pub fn elliptic_cone(a: f64, b: f64) -> Self {
+        Self::from_coefficients([
+            a.powi(-2),
+            -1.0,
+            b.powi(-2),
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+        ])
+    }
+
+    pub fn with_transform(mut self, t: Matrix<4>) -> Self {
+        self.transform.set(t);
+        self
+    }
+
+    pub fn with_material(mut self, m: Material) -> Self {
+        self.material = m;
+        self
+    }
+
+    pub fn with_clip(mut self, clip: BoundingBox) -> Self {
+        self.clip = Some(clip);
+        self
+    }
+
+    fn is_kept(&self, point: Tuple) -> bool {
+        match self.clip {
+            None => true,
+            Some(clip) => BoundingBox::new(
+                clip.min - Tuple::Vector(EPSILON, EPSILON, EPSILON),
+                clip.max + Tuple::Vector(EPSILON, EPSILON, EPSILON),
+            )
+            .contains(point),
+        }
+    }
+}
+
+impl Intersectable for Quadric {
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn transformation(&self) -> &Transformation {
+        &self.transform
+    }
+
+    fn transform_mut(&mut self) -> &mut Transformation {
+        &mut self.transform
+    }
+
+    fn intersect_in_object_space(&self, object_space_ray: &Ray) -> Hits {
+        if let Some(clip) = self.clip {
+            if !clip.intersects(object_space_ray) {
+                return Hits::new();
+            }
+        }
+        let [qa, qb, qc, qd, qe, qf, qg, qh, qi, qj] = self.coefficients;
+        let (o, d) = (object_space_ray.origin, object_space_ray.direction);
+        let a = qa * d.x * d.x
+            + qb * d.y * d.y
+            + qc * d.z * d.z
+            + qd * d.x * d.y
+            + qe * d.x * d.z
+            + qf * d.y * d.z;
+        let b = 2.0 * (qa * o.x * d.x + qb * o.y * d.y + qc * o.z * d.z)
+            + qd * (o.x * d.y + o.y * d.x)
+            + qe * (o.x * d.z + o.z * d.x)
+            + qf * (o.y * d.z + o.z * d.y)
+            + qg * d.x
+            + qh * d.y
+            + qi * d.z;
+        let c = qa * o.x * o.x
+            + qb * o.y * o.y
+            + qc * o.z * o.z
+            + qd * o.x * o.y
+            + qe * o.x * o.z
+            + qf * o.y * o.z
+            + qg * o.x
+            + qh * o.y
+            + qi * o.z
+            + qj;
+        solve_quadratic(a, b, c)
+            .into_iter()
+            .filter(|t| self.is_kept(object_space_ray.position(*t)))
+            .collect()
+    }
+
+    fn normal_at_in_object_space(&self, p: Tuple) -> Tuple {
+        let [qa, qb, qc, qd, qe, qf, qg, qh, qi, _] = self.coefficients;
+        Tuple::Vector(
+            2.0 * qa * p.x + qd * p.y + qe * p.z + qg,
+            2.0 * qb * p.y + qd * p.x + qf * p.z + qh,
+            2.0 * qc * p.z + qe * p.x + qf * p.y + qi,
+        )
+        .normalize()
+    }
+}
+
+impl From<Quadric> for Body {
+    fn from(q: Quadric) -> Self {
+        Body::Quadric(q)
+    }
+}
+
+impl IntoBody for Quadric {
+    fn into_body(&self) -> Body {
+        Body::Quadric(self.clone())
+    }
+}
+
+encode_fields!(Quadric {
+    transform,
+    material,
+    coefficients,
+    clip
+});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_distances(actual: Hits, expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len(), "distances were {actual:?}");
+        for (t, expected) in actual.iter().zip(expected) {
+            assert!((t - expected).abs() < 1e-9, "distances were {actual:?}");
+        }
+    }
+
+    #[test]
+    fn unit_ellipsoid_is_a_sphere() {
+        let quadric = Quadric::ellipsoid(1.0, 1.0, 1.0);
+        let ray = Ray::new(Tuple::Point(0.0, 0.0, -5.0), Tuple::Vector(0.0, 0.0, 1.0));
+        assert_distances(quadric.intersect_in_object_space(&ray), &[4.0, 6.0]);
+        assert_eq!(
+            quadric.normal_at_in_object_space(Tuple::Point(0.0, 0.6, 0.8)),
+            Tuple::Vector(0.0, 0.6, 0.8)
+        );
+    }
+
+    #[test]
+    fn ellipsoid_axes_are_independent() {
+        let quadric = Quadric::ellipsoid(3.0, 1.0, 2.0);
+        let along_x = Ray::new(Tuple::Point(-5.0, 0.0, 0.0), Tuple::Vector(1.0, 0.0, 0.0));
+        assert_distances(quadric.intersect_in_object_space(&along_x), &[2.0, 8.0]);
+        let along_z = Ray::new(Tuple::Point(0.0, 0.0, -5.0), Tuple::Vector(0.0, 0.0, 1.0));
+        assert_distances(quadric.intersect_in_object_space(&along_z), &[3.0, 7.0]);
+    }
+
+    #[test]
+    fn paraboloid_is_hit_once_along_its_axis() {
+        let quadric = Quadric::paraboloid(1.0, 1.0);
+        let down = Ray::new(Tuple::Point(0.0, 5.0, 0.0), Tuple::Vector(0.0, -1.0, 0.0));
+        assert_distances(quadric.intersect_in_object_space(&down), &[5.0]);
+        let across = Ray::new(Tuple::Point(-5.0, 4.0, 0.0), Tuple::Vector(1.0, 0.0, 0.0));
+        assert_distances(quadric.intersect_in_object_space(&across), &[3.0, 7.0]);
+        assert_eq!(
+            quadric.normal_at_in_object_space(Tuple::Point(0.0, 0.0, 0.0)),
+            Tuple::Vector(0.0, -1.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn hyperboloids_have_their_waist_or_gap() {
+        let tower = Quadric::hyperboloid_of_one_sheet(1.0, 1.0);
+        let down_the_middle = Ray::new(Tuple::Point(0.0, 5.0, 0.0), Tuple::Vector(0.0, -1.0, 0.0));
+        assert!(tower.intersect_in_object_space(&down_the_middle).is_empty());
+        let sheets = Quadric::hyperboloid_of_two_sheets(1.0, 1.0);
+        assert_distances(
+            sheets.intersect_in_object_space(&down_the_middle),
+            &[4.0, 6.0],
+        );
+    }
+
+    #[test]
+    fn clipping_keeps_only_the_inside_of_the_box() {
+        let bowl = Quadric::paraboloid(1.0, 1.0).with_clip(BoundingBox::new(
+            Tuple::Point(-2.0, 0.0, -2.0),
+            Tuple::Point(2.0, 1.0, 2.0),
+        ));
+        let across = Ray::new(Tuple::Point(-5.0, 4.0, 0.0), Tuple::Vector(1.0, 0.0, 0.0));
+        assert!(bowl.intersect_in_object_space(&across).is_empty());
+        let across = Ray::new(Tuple::Point(-5.0, 0.25, 0.0), Tuple::Vector(1.0, 0.0, 0.0));
+        assert_distances(bowl.intersect_in_object_space(&across), &[4.5, 5.5]);
+    }
+}
@@ -1,15 +1,43 @@
 use crate::{matrix::Matrix, tuple::Tuple};
 
+/// Only hits strictly between ```t_min``` and ```t_max``` count; ```Ray::new``` leaves both
+/// unbounded, so surfaces behind the origin are reported too.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Ray {
     pub origin: Tuple,    // basically Point
     pub direction: Tuple, // basically Vector
+    pub t_min: f64,
+    pub t_max: f64,
 }
 
 impl Ray {
     #[inline]
     pub fn new(origin: Tuple, direction: Tuple) -> Self {
-        Self { origin, direction }
+        Self {
+            origin,
+            direction,
+            t_min: f64::NEG_INFINITY,
+            t_max: f64::INFINITY,
+        }
+    }
+
+    /// ```
+    /// use raytracer_rust::ray::Ray;
+    /// use raytracer_rust::tuple::Tuple;
+    /// let ray = Ray::new(Tuple::Point(0, 0, 0), Tuple::Vector(0, 0, 1)).with_interval(0.5, 2.0);
+    /// assert!(ray.contains(1.0));
+    /// assert!(!ray.contains(0.5));
+    /// assert!(!ray.contains(3.0));
+    /// ```
+    pub fn with_interval(mut self, t_min: f64, t_max: f64) -> Self {
+        self.t_min = t_min;
+        self.t_max = t_max;
+        self
+    }
+
+    #[inline]
+    pub fn contains(&self, t: f64) -> bool {
+        t > self.t_min && t < self.t_max
     }
 
     /// ```
@@ -33,9 +61,11 @@ impl Ray {
     /// assert_eq!(ray.transform(transform), Ray::new(Tuple::Point(2.0, 6.0, 12.0), Tuple::Vector(0.0, 3.0, 0.0)));
     /// ```
     pub fn transform(&self, t: Matrix<4>) -> Self {
+        // the direction isn't renormalized, so distances along the ray carry over
         Self {
             origin: t * self.origin,
             direction: t * self.direction,
+            ..*self
         }
     }
 }
@@ -0,0 +1,46 @@
+/// Small deterministic random number generator (SplitMix64).
+/// The same seed always produces the same sequence, which keeps renders reproducible.
+#[derive(Clone, Debug)]
+pub struct Sampler {
+    state: u64,
+}
+
+impl Sampler {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Seeds a sampler from several values, e.g. a pass number and pixel coordinates.
+    pub fn from_seeds(seeds: &[u64]) -> Self {
+        let mut sampler = Self::new(0x2545_f491_4f6c_dd1d);
+        for seed in seeds {
+            sampler.state ^= seed;
+            sampler.next_u64();
+        }
+        sampler
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniformly distributed value in [0, 1).
+    /// ```
+    /// use raytracer_rust::sampler::Sampler;
+    /// let mut a = Sampler::new(7);
+    /// let mut b = Sampler::new(7);
+    /// for _ in 0..100 {
+    ///     let x = a.next_f64();
+    ///     assert!((0.0..1.0).contains(&x));
+    ///     assert_eq!(x, b.next_f64());
+    /// }
+    /// ```
+    pub fn next_f64(&mut self) -> f64 {
+        // 53 random bits fill the mantissa of an f64 exactly
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
@@ -0,0 +1,545 @@
+use crate::{
+    body::{Body, Hits, Intersectable, IntoBody},
+    encoding::{encode_fields, unknown_tag, Encode},
+    material::{Material, Phong, Refractive},
+    matrix::Matrix,
+    ray::Ray,
+    transformation::Transformation,
+    tuple::Tuple,
+};
+
+/// Tree of signed distance functions: negative inside, positive outside.
+/// Build it from the primitive constructors and chain the combinators, e.g.
+/// ```DistanceField::sphere(1.0).smooth_union(DistanceField::cuboid(0.5, 1.5, 0.5), 0.3)```.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DistanceField {
+    Sphere(f64),
+    /// Box with the given half extents.
+    Cuboid(f64, f64, f64),
+    /// Ring around the y axis with major and minor radius.
+    Torus(f64, f64),
+    /// Capped cylinder around the y axis with radius and half height.
+    Cylinder(f64, f64),
+    Union(Box<DistanceField>, Box<DistanceField>),
+    Intersection(Box<DistanceField>, Box<DistanceField>),
+    /// The first field with the second one carved out of it.
+    Subtraction(Box<DistanceField>, Box<DistanceField>),
+    SmoothUnion(Box<DistanceField>, Box<DistanceField>, f64),
+    SmoothSubtraction(Box<DistanceField>, Box<DistanceField>, f64),
+    /// Grows the surface outwards, rounding off its edges.
+    Round(Box<DistanceField>, f64),
+    /// Rotates around the y axis by the given radians per unit of height.
+    Twist(Box<DistanceField>, f64),
+    /// Repeats the field in cells of the given size along each axis, 0.0 meaning no repetition.
+    Repeat(Box<DistanceField>, f64, f64, f64),
+    Translate(Box<DistanceField>, f64, f64, f64),
+}
+
+impl DistanceField {
+    pub fn sphere(radius: f64) -> Self {
+        DistanceField::Sphere(radius)
+    }
+
+    pub fn cuboid(half_x: f64, half_y: f64, half_z: f64) -> Self {
+        DistanceField::Cuboid(half_x, half_y, half_z)
+    }
+
+    pub fn torus(major_radius: f64, minor_radius: f64) -> Self {
+        DistanceField::Torus(major_radius, minor_radius)
+    }
+
+    pub fn cylinder(radius: f64, half_height: f64) -> Self {
+        DistanceField::Cylinder(radius, half_height)
+    }
+
+    pub fn union(self, other: Self) -> Self {
+        DistanceField::Union(Box::new(self), Box::new(other))
+    }
+
+    pub fn intersection(self, other: Self) -> Self {
+        DistanceField::Intersection(Box::new(self), Box::new(other))
+    }
+
+    pub fn subtract(self, other: Self) -> Self {
+        DistanceField::Subtraction(Box::new(self), Box::new(other))
+    }
+
+    /// Union that blends the surfaces together over a distance of about ```k```.
+    pub fn smooth_union(self, other: Self, k: f64) -> Self {
+        DistanceField::SmoothUnion(Box::new(self), Box::new(other), k)
+    }
+
+    pub fn smooth_subtract(self, other: Self, k: f64) -> Self {
+        DistanceField::SmoothSubtraction(Box::new(self), Box::new(other), k)
+    }
+
+    pub fn round(self, radius: f64) -> Self {
+        DistanceField::Round(Box::new(self), radius)
+    }
+
+    pub fn twist(self, radians_per_unit: f64) -> Self {
+        DistanceField::Twist(Box::new(self), radians_per_unit)
+    }
+
+    pub fn repeat(self, x: f64, y: f64, z: f64) -> Self {
+        DistanceField::Repeat(Box::new(self), x, y, z)
+    }
+
+    pub fn translate(self, x: f64, y: f64, z: f64) -> Self {
+        DistanceField::Translate(Box::new(self), x, y, z)
+    }
+
+    /// ```
+    /// use raytracer_rust::sdf::DistanceField;
+    /// use raytracer_rust::tuple::Tuple;
+    /// let field = DistanceField::sphere(1.0).subtract(DistanceField::cuboid(2.0, 2.0, 0.5));
+    /// assert_eq!(field.distance(Tuple::Point(0.0, 0.0, -3.0)), 2.0);
+    /// assert_eq!(field.distance(Tuple::Point(0.0, 0.0, 0.25)), 0.25);
+    /// assert!(field.distance(Tuple::Point(0.0, 0.0, 0.75)) < 0.0);
+    /// ```
+    pub fn distance(&self, point: Tuple) -> f64 {
+        self.distance_at(point.x, point.y, point.z)
+    }
+
+    fn distance_at(&self, x: f64, y: f64, z: f64) -> f64 {
+        match self {
+            DistanceField::Sphere(radius) => length(x, y, z) - radius,
+            DistanceField::Cuboid(hx, hy, hz) => {
+                let (qx, qy, qz) = (x.abs() - hx, y.abs() - hy, z.abs() - hz);
+                length(qx.max(0.0), qy.max(0.0), qz.max(0.0)) + qx.max(qy).max(qz).min(0.0)
+            }
+            DistanceField::Torus(major, minor) => {
+                length((x * x + z * z).sqrt() - major, y, 0.0) - minor
+            }
+            DistanceField::Cylinder(radius, half_height) => {
+                let dx = (x * x + z * z).sqrt() - radius;
+                let dy = y.abs() - half_height;
+                dx.max(dy).min(0.0) + length(dx.max(0.0), dy.max(0.0), 0.0)
+            }
+            DistanceField::Union(a, b) => a.distance_at(x, y, z).min(b.distance_at(x, y, z)),
+            DistanceField::Intersection(a, b) => a.distance_at(x, y, z).max(b.distance_at(x, y, z)),
+            DistanceField::Subtraction(a, b) => a.distance_at(x, y, z).max(-b.distance_at(x, y, z)),
+            DistanceField::SmoothUnion(a, b, k) => {
+                let (da, db) = (a.distance_at(x, y, z), b.distance_at(x, y, z));
+                let h = (0.5 + 0.5 * (db - da) / k).clamp(0.0, 1.0);
+                db + (da - db) * h - k * h * (1.0 - h)
+            }
+            DistanceField::SmoothSubtraction(a, b, k) => {
+                let (da, db) = (a.distance_at(x, y, z), b.distance_at(x, y, z));
+                let h = (0.5 - 0.5 * (da + db) / k).clamp(0.0, 1.0);
+                da + (-db - da) * h + k * h * (1.0 - h)
+            }
+            DistanceField::Round(a, radius) => a.distance_at(x, y, z) - radius,
+            DistanceField::Twist(a, radians_per_unit) => {
+                let (sin, cos) = (radians_per_unit * y).sin_cos();
+                a.distance_at(cos * x - sin * z, y, sin * x + cos * z)
+            }
+            DistanceField::Repeat(a, cx, cy, cz) => {
+                let cell = |v: f64, size: f64| {
+                    if size > 0.0 {
+                        v - size * (v / size).round()
+                    } else {
+                        v
+                    }
+                };
+                a.distance_at(cell(x, *cx), cell(y, *cy), cell(z, *cz))
+            }
+            DistanceField::Translate(a, dx, dy, dz) => a.distance_at(x - dx, y - dy, z - dz),
+        }
+    }
+}
+
+fn length(x: f64, y: f64, z: f64) -> f64 {
+    (x * x + y * y + z * z).sqrt()
+}
+
+/// Body whose surface is the zero set of a ```DistanceField```, found by sphere tracing.
+#[derive(Clone, Debug)]
+pub struct Sdf {
+    pub transform: Transformation,
+    pub material: Material,
+    pub field: DistanceField,
+    /// Smallest step taken while marching, which also sets the thinnest feature it can find.
+    pub epsilon: f64,
+    pub max_steps: usize,
+    /// How far along the ray to look for the surface, and how far behind its origin for
+    /// transparent bodies.
+    pub max_distance: f64,
+    /// Fraction of the distance bound taken per step. Lower it for fields such as
+    /// twists that overestimate the distance to their surface.
+    pub step_scale: f64,
+}
+
+impl PartialEq for Sdf {
+    fn eq(&self, other: &Self) -> bool {
+        self.transform == other.transform
+            && self.material == other.material
+            && self.field == other.field
+            && self.epsilon == other.epsilon
+            && self.max_steps == other.max_steps
+            && self.max_distance == other.max_distance
+            && self.step_scale == other.step_scale
+    }
+}
+
+impl Sdf {
+    pub fn new(transform: Matrix<4>, material: Material, field: DistanceField) -> Self {
+        Self {
+            transform: transform.into(),
+            material,
+            field,
+            epsilon: 0.0001,
+            max_steps: 512,
+            max_distance: 100.0,
+            step_scale: 1.0,
+        }
+    }
+
+    pub fn with_transform(mut self, t: Matrix<4>) -> Self {
+        self.transform.set(t);
+        self
+    }
+
+    pub fn with_material(mut self, m: Material) -> Self {
+        self.material = m;
+        self
+    }
+
+    pub fn with_epsilon(mut self, epsilon: f64) -> Self {
+        self.epsilon = epsilon;
+        self
+    }
+
+    pub fn with_limits(mut self, max_steps: usize, max_distance: f64) -> Self {
+        self.max_steps = max_steps;
+        self.max_distance = max_distance;
+        self
+    }
+
+    pub fn with_step_scale(mut self, step_scale: f64) -> Self {
+        self.step_scale = step_scale;
+        self
+    }
+}
+
+impl Sdf {
+    /// Sphere traces from ```from``` towards ```to```, which may lie behind it, adding every
+    /// surface crossed to ```res```.
+    fn march(&self, distance_at: &impl Fn(f64) -> f64, from: f64, to: f64, res: &mut Hits) {
+        let forward = if to >= from { 1.0 } else { -1.0 };
+        let mut t = from;
+        let mut distance = distance_at(t);
+        for _ in 0..self.max_steps {
+            if (to - t) * forward < 0.0 {
+                break;
+            }
+            // never stall in front of a surface the ray only grazes
+            let next_t = t + forward * (distance.abs() * self.step_scale).max(self.epsilon);
+            let next_distance = distance_at(next_t);
+            if (distance < 0.0) != (next_distance < 0.0) {
+                res.push(refine_crossing(distance_at, t, next_t, distance < 0.0));
+            }
+            t = next_t;
+            distance = next_distance;
+        }
+    }
+}
+
+impl From<DistanceField> for Sdf {
+    fn from(field: DistanceField) -> Self {
+        Self::new(Matrix::Identity(), Material::Phong(Phong::default()), field)
+    }
+}
+
+impl Intersectable for Sdf {
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn transformation(&self) -> &Transformation {
+        &self.transform
+    }
+
+    fn transform_mut(&mut self) -> &mut Transformation {
+        &mut self.transform
+    }
+
+    fn intersect_in_object_space(&self, object_space_ray: &Ray) -> Hits {
+        let scale = object_space_ray.direction.magnitude();
+        let direction = object_space_ray.direction * (1.0 / scale);
+        let distance_at = |t: f64| self.field.distance(object_space_ray.origin + direction * t);
+        let t_min = (object_space_ray.t_min * scale).max(-self.max_distance);
+        let t_max = (object_space_ray.t_max * scale).min(self.max_distance);
+
+        // March forward from the origin first, so nothing behind the ray can use up the
+        // step budget before the surface in front of it is found.
+        let mut res = Hits::new();
+        self.march(&distance_at, t_min.max(0.0), t_max, &mut res);
+        // Like the analytic shapes, report surfaces behind the origin too when refraction
+        // needs them to know what the ray is inside of. They get a budget of their own.
+        if t_min < 0.0 && self.material.transparency() != 0.0 {
+            self.march(&distance_at, t_max.min(0.0), t_min, &mut res);
+            res.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        }
+        res.iter().map(|t| t / scale).collect()
+    }
+
+    fn normal_at_in_object_space(&self, object_space_point: Tuple) -> Tuple {
+        let h = self.epsilon;
+        let slope = |offset: Tuple| {
+            self.field.distance(object_space_point + offset)
+                - self.field.distance(object_space_point - offset)
+        };
+        Tuple::Vector(
+            slope(Tuple::Vector(h, 0.0, 0.0)),
+            slope(Tuple::Vector(0.0, h, 0.0)),
+            slope(Tuple::Vector(0.0, 0.0, h)),
+        )
+        .normalize()
+    }
+}
+
+/// Bisects the step in which the field changed sign down to the surface itself.
+fn refine_crossing(
+    distance_at: impl Fn(f64) -> f64,
+    mut low: f64,
+    mut high: f64,
+    low_inside: bool,
+) -> f64 {
+    for _ in 0..32 {
+        let middle = 0.5 * (low + high);
+        if (distance_at(middle) < 0.0) == low_inside {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    0.5 * (low + high)
+}
+
+impl From<Sdf> for Body {
+    fn from(s: Sdf) -> Self {
+        Body::Sdf(s)
+    }
+}
+
+impl IntoBody for Sdf {
+    fn into_body(&self) -> Body {
+        Body::Sdf(self.clone())
+    }
+}
+
+encode_fields!(Sdf {
+    transform,
+    material,
+    field,
+    epsilon,
+    max_steps,
+    max_distance,
+    step_scale,
+});
+
+impl Encode for DistanceField {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            DistanceField::Sphere(radius) => {
+                out.push(0);
+                radius.encode(out);
+            }
+            DistanceField::Cuboid(x, y, z) => {
+                out.push(1);
+                [*x, *y, *z].encode(out);
+            }
+            DistanceField::Torus(major, minor) => {
+                out.push(2);
+                [*major, *minor].encode(out);
+            }
+            DistanceField::Cylinder(radius, height) => {
+                out.push(3);
+                [*radius, *height].encode(out);
+            }
+            DistanceField::Union(a, b) => {
+                out.push(4);
+                a.encode(out);
+                b.encode(out);
+            }
+            DistanceField::Intersection(a, b) => {
+                out.push(5);
+                a.encode(out);
+                b.encode(out);
+            }
+            DistanceField::Subtraction(a, b) => {
+                out.push(6);
+                a.encode(out);
+                b.encode(out);
+            }
+            DistanceField::SmoothUnion(a, b, k) => {
+                out.push(7);
+                a.encode(out);
+                b.encode(out);
+                k.encode(out);
+            }
+            DistanceField::SmoothSubtraction(a, b, k) => {
+                out.push(8);
+                a.encode(out);
+                b.encode(out);
+                k.encode(out);
+            }
+            DistanceField::Round(field, radius) => {
+                out.push(9);
+                field.encode(out);
+                radius.encode(out);
+            }
+            DistanceField::Twist(field, rate) => {
+                out.push(10);
+                field.encode(out);
+                rate.encode(out);
+            }
+            DistanceField::Repeat(field, x, y, z) => {
+                out.push(11);
+                field.encode(out);
+                [*x, *y, *z].encode(out);
+            }
+            DistanceField::Translate(field, x, y, z) => {
+                out.push(12);
+                field.encode(out);
+                [*x, *y, *z].encode(out);
+            }
+        }
+    }
+
+    fn decode(bytes: &mut &[u8]) -> std::io::Result<Self> {
+        let field = |bytes: &mut &[u8]| Box::<DistanceField>::decode(bytes);
+        Ok(match u8::decode(bytes)? {
+            0 => DistanceField::Sphere(f64::decode(bytes)?),
+            1 => {
+                let [x, y, z] = <[f64; 3]>::decode(bytes)?;
+                DistanceField::Cuboid(x, y, z)
+            }
+            2 => {
+                let [major, minor] = <[f64; 2]>::decode(bytes)?;
+                DistanceField::Torus(major, minor)
+            }
+            3 => {
+                let [radius, height] = <[f64; 2]>::decode(bytes)?;
+                DistanceField::Cylinder(radius, height)
+            }
+            4 => DistanceField::Union(field(bytes)?, field(bytes)?),
+            5 => DistanceField::Intersection(field(bytes)?, field(bytes)?),
+            6 => DistanceField::Subtraction(field(bytes)?, field(bytes)?),
+            7 => DistanceField::SmoothUnion(field(bytes)?, field(bytes)?, f64::decode(bytes)?),
+            8 => {
+                DistanceField::SmoothSubtraction(field(bytes)?, field(bytes)?, f64::decode(bytes)?)
+            }
+            9 => DistanceField::Round(field(bytes)?, f64::decode(bytes)?),
+            10 => DistanceField::Twist(field(bytes)?, f64::decode(bytes)?),
+            11 => {
+                let field = field(bytes)?;
+                let [x, y, z] = <[f64; 3]>::decode(bytes)?;
+                DistanceField::Repeat(field, x, y, z)
+            }
+            12 => {
+                let field = field(bytes)?;
+                let [x, y, z] = <[f64; 3]>::decode(bytes)?;
+                DistanceField::Translate(field, x, y, z)
+            }
+            tag => return Err(unknown_tag("DistanceField", tag)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_distances(actual: Hits, expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len(), "distances were {actual:?}");
+        for (t, expected) in actual.iter().zip(expected) {
+            assert!((t - expected).abs() < 1e-3, "distances were {actual:?}");
+        }
+    }
+
+    #[test]
+    fn traced_sphere_matches_the_analytic_one() {
+        let sdf = Sdf::from(DistanceField::sphere(1.0));
+        let ray = Ray::new(Tuple::Point(0.0, 0.0, -5.0), Tuple::Vector(0.0, 0.0, 1.0));
+        assert_distances(sdf.intersect_in_object_space(&ray), &[4.0, 6.0]);
+        // from inside, the surface behind the origin is only needed, and reported, when
+        // the body refracts
+        let ray = Ray::new(Tuple::Point(0.0, 0.0, 0.0), Tuple::Vector(0.0, 0.0, 2.0));
+        assert_distances(sdf.intersect_in_object_space(&ray), &[0.5]);
+        let glass = sdf.clone().with_material(Material::Phong(Phong {
+            transparency: 1.0,
+            ..Default::default()
+        }));
+        assert_distances(glass.intersect_in_object_space(&ray), &[-0.5, 0.5]);
+        let ray = Ray::new(Tuple::Point(0.0, 2.0, -5.0), Tuple::Vector(0.0, 0.0, 1.0));
+        assert!(sdf.intersect_in_object_space(&ray).is_empty());
+    }
+
+    #[test]
+    fn surfaces_behind_the_origin_do_not_use_up_the_steps() {
+        // a slab grazing the ray far behind its origin forces the smallest steps there
+        let field = DistanceField::sphere(1.0)
+            .union(DistanceField::cuboid(0.5, 0.5, 40.0).translate(0.5005, 0.0, -50.0));
+        let ray = Ray::new(Tuple::Point(0.0, 0.0, -5.0), Tuple::Vector(0.0, 0.0, 1.0));
+        for transparency in [0.0, 1.0] {
+            let sdf = Sdf::from(field.clone()).with_material(Material::Phong(Phong {
+                transparency,
+                ..Default::default()
+            }));
+            let xs = sdf.intersect_in_object_space(&ray);
+            let ahead: Vec<f64> = xs.iter().copied().filter(|t| *t > 0.0).collect();
+            assert_eq!(ahead.len(), 2, "distances were {xs:?}");
+            assert!((ahead[0] - 4.0).abs() < 1e-6 && (ahead[1] - 6.0).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn normals_follow_the_gradient() {
+        let sdf = Sdf::from(DistanceField::cuboid(1.0, 1.0, 1.0).round(0.1));
+        assert_eq!(
+            sdf.normal_at_in_object_space(Tuple::Point(1.1, 0.2, 0.3)),
+            Tuple::Vector(1.0, 0.0, 0.0)
+        );
+        let sphere = Sdf::from(DistanceField::sphere(1.0));
+        let point = Tuple::Point(0.0, 0.6, 0.8);
+        assert_eq!(
+            sphere.normal_at_in_object_space(point),
+            Tuple::Vector(0.0, 0.6, 0.8)
+        );
+    }
+
+    #[test]
+    fn combinators_shape_the_field() {
+        let ring = DistanceField::cylinder(1.0, 0.5).subtract(DistanceField::cylinder(0.5, 1.0));
+        let sdf = Sdf::from(ring);
+        let ray = Ray::new(Tuple::Point(-5.0, 0.0, 0.0), Tuple::Vector(1.0, 0.0, 0.0));
+        assert_distances(sdf.intersect_in_object_space(&ray), &[4.0, 4.5, 5.5, 6.0]);
+
+        let row = DistanceField::sphere(0.25).repeat(1.0, 0.0, 0.0);
+        assert!(row.distance(Tuple::Point(3.0, 0.0, 0.0)) < 0.0);
+        assert!(row.distance(Tuple::Point(3.5, 0.0, 0.0)) > 0.0);
+        assert!(row.distance(Tuple::Point(0.0, 3.0, 0.0)) > 0.0);
+
+        // the blend fills in the gap between two spheres that don't touch
+        let a = DistanceField::sphere(1.0).translate(-1.1, 0.0, 0.0);
+        let b = DistanceField::sphere(1.0).translate(1.1, 0.0, 0.0);
+        let origin = Tuple::Point(0.0, 0.0, 0.0);
+        assert!(a.clone().union(b.clone()).distance(origin) > 0.0);
+        assert!(a.smooth_union(b, 0.5).distance(origin) < 0.0);
+    }
+
+    #[test]
+    fn twisted_box_is_still_found() {
+        let field = DistanceField::cuboid(0.5, 2.0, 0.5).twist(0.5);
+        let sdf = Sdf::from(field).with_step_scale(0.5);
+        let ray = Ray::new(Tuple::Point(0.0, 0.0, -5.0), Tuple::Vector(0.0, 0.0, 1.0));
+        // there is no twist at y = 0
+        assert_distances(sdf.intersect_in_object_space(&ray), &[4.5, 5.5]);
+    }
+}
@@ -0,0 +1,250 @@
+//! Real roots of low degree polynomials, returned in ascending order.
+
+use crate::{consts::PI, inline_vec::InlineVec};
+
+/// Roots of a polynomial of degree four or less, kept on the stack so intersecting a
+/// ray never allocates.
+pub type Roots = InlineVec<f64, 4>;
+
+// coefficients smaller than this (relative to the leading one) are treated as zero
+const NEAR_ZERO: f64 = 1e-12;
+
+/// Roots of ```a·x² + b·x + c```. A double root is reported once.
+/// ```
+/// use raytracer_rust::solver::solve_quadratic;
+/// assert_eq!(solve_quadratic(1.0, -3.0, 2.0), vec![1.0, 2.0]);
+/// assert_eq!(solve_quadratic(1.0, 0.0, 1.0), vec![]);
+/// ```
+pub fn solve_quadratic(a: f64, b: f64, c: f64) -> Roots {
+    if a.abs() < NEAR_ZERO {
+        return if b.abs() < NEAR_ZERO {
+            Roots::new()
+        } else {
+            Roots::from([-c / b])
+        };
+    }
+    let disc = b * b - 4.0 * a * c;
+    if disc < 0.0 {
+        return Roots::new();
+    }
+    if disc == 0.0 {
+        return Roots::from([-b / (2.0 * a)]);
+    }
+    // avoid subtracting two nearly equal numbers
+    let q = -0.5 * (b + b.signum() * disc.sqrt());
+    let (x1, x2) = if q == 0.0 {
+        let root = (-c / a).sqrt();
+        (-root, root)
+    } else {
+        (q / a, c / q)
+    };
+    if x1 < x2 {
+        Roots::from([x1, x2])
+    } else {
+        Roots::from([x2, x1])
+    }
+}
+
+/// Roots of ```a·x³ + b·x² + c·x + d```.
+/// ```
+/// use raytracer_rust::solver::solve_cubic;
+/// let roots = solve_cubic(2.0, -12.0, 22.0, -12.0);
+/// assert_eq!(roots.len(), 3);
+/// for (root, expected) in roots.iter().zip([1.0, 2.0, 3.0]) {
+///     assert!((root - expected).abs() < 1e-9);
+/// }
+/// ```
+pub fn solve_cubic(a: f64, b: f64, c: f64, d: f64) -> Roots {
+    if a.abs() < NEAR_ZERO {
+        return solve_quadratic(b, c, d);
+    }
+    let (a2, a1, a0) = (b / a, c / a, d / a);
+    let roots = solve_normalized_cubic(a2, a1, a0)
+        .into_iter()
+        .map(|x| polish(x, &[1.0, a2, a1, a0]))
+        .collect();
+    sorted_and_deduplicated(roots)
+}
+
+/// Roots of ```a·x⁴ + b·x³ + c·x² + d·x + e```, found with Ferrari's method and
+/// then refined with a few Newton steps against the original polynomial.
+/// ```
+/// use raytracer_rust::solver::solve_quartic;
+/// // (x - 1)(x - 2)(x - 3)(x - 4)
+/// let roots = solve_quartic(1.0, -10.0, 35.0, -50.0, 24.0);
+/// assert_eq!(roots.len(), 4);
+/// for (root, expected) in roots.iter().zip([1.0, 2.0, 3.0, 4.0]) {
+///     assert!((root - expected).abs() < 1e-9);
+/// }
+/// ```
+pub fn solve_quartic(a: f64, b: f64, c: f64, d: f64, e: f64) -> Roots {
+    if a.abs() < NEAR_ZERO {
+        return solve_cubic(b, c, d, e);
+    }
+    let (a3, a2, a1, a0) = (b / a, c / a, d / a, e / a);
+
+    // substitute x = y - a3/4 to get y⁴ + p·y² + q·y + r
+    let a3_sq = a3 * a3;
+    let p = a2 - 3.0 / 8.0 * a3_sq;
+    let q = a3_sq * a3 / 8.0 - a3 * a2 / 2.0 + a1;
+    let r = -3.0 / 256.0 * a3_sq * a3_sq + a3_sq * a2 / 16.0 - a3 * a1 / 4.0 + a0;
+
+    let mut ys = Roots::new();
+    if r.abs() < NEAR_ZERO {
+        // y·(y³ + p·y + q) = 0
+        ys.push(0.0);
+        ys.extend(solve_normalized_cubic(0.0, p, q));
+    } else if q.abs() < NEAR_ZERO {
+        // biquadratic in y²
+        for z in solve_quadratic(1.0, p, r) {
+            if z >= 0.0 {
+                ys.push(z.sqrt());
+                ys.push(-z.sqrt());
+            }
+        }
+    } else {
+        // any real root of the resolvent cubic splits the quartic into two quadratics
+        let z = solve_normalized_cubic(-p / 2.0, -r, r * p / 2.0 - q * q / 8.0)
+            .into_iter()
+            .fold(f64::NEG_INFINITY, f64::max);
+        let u = z * z - r;
+        let v = 2.0 * z - p;
+        if u < -NEAR_ZERO || v < -NEAR_ZERO {
+            return Roots::new();
+        }
+        let u = u.max(0.0).sqrt();
+        let v = v.max(0.0).sqrt();
+        let v = if q < 0.0 { -v } else { v };
+        ys.extend(solve_quadratic(1.0, v, z - u));
+        ys.extend(solve_quadratic(1.0, -v, z + u));
+    }
+
+    let coefficients = [1.0, a3, a2, a1, a0];
+    let roots = ys
+        .into_iter()
+        .map(|y| polish(y - a3 / 4.0, &coefficients))
+        .collect();
+    sorted_and_deduplicated(roots)
+}
+
+/// Roots of ```x³ + a2·x² + a1·x + a0```, unsorted.
+fn solve_normalized_cubic(a2: f64, a1: f64, a0: f64) -> Roots {
+    // substitute x = y - a2/3 to get y³ + p·y + q
+    let p = a1 - a2 * a2 / 3.0;
+    let q = 2.0 * a2 * a2 * a2 / 27.0 - a2 * a1 / 3.0 + a0;
+    let shift = a2 / 3.0;
+    let disc = (q / 2.0).powi(2) + (p / 3.0).powi(3);
+
+    if disc.abs() < NEAR_ZERO {
+        if q.abs() < NEAR_ZERO {
+            Roots::from([-shift])
+        } else {
+            let u = (-q / 2.0).cbrt();
+            Roots::from([2.0 * u - shift, -u - shift])
+        }
+    } else if disc < 0.0 {
+        // three real roots, found with trigonometry rather than complex cube roots
+        let phi = ((-q / 2.0) / (-(p / 3.0).powi(3)).sqrt())
+            .clamp(-1.0, 1.0)
+            .acos()
+            / 3.0;
+        let m = 2.0 * (-p / 3.0).sqrt();
+        Roots::from([
+            m * phi.cos() - shift,
+            m * (phi + 2.0 * PI / 3.0).cos() - shift,
+            m * (phi + 4.0 * PI / 3.0).cos() - shift,
+        ])
+    } else {
+        let sqrt_disc = disc.sqrt();
+        let u = (-q / 2.0 + sqrt_disc).cbrt();
+        let v = (-q / 2.0 - sqrt_disc).cbrt();
+        Roots::from([u + v - shift])
+    }
+}
+
+/// A few Newton steps towards the nearest root of the polynomial with the given
+/// coefficients (highest degree first), kept only while they improve the residual.
+fn polish(mut x: f64, coefficients: &[f64]) -> f64 {
+    for _ in 0..4 {
+        let (value, derivative) = evaluate(x, coefficients);
+        if derivative == 0.0 || value == 0.0 {
+            break;
+        }
+        let next = x - value / derivative;
+        if evaluate(next, coefficients).0.abs() >= value.abs() {
+            break;
+        }
+        x = next;
+    }
+    x
+}
+
+/// Value and derivative of a polynomial at ```x``` using Horner's scheme.
+fn evaluate(x: f64, coefficients: &[f64]) -> (f64, f64) {
+    coefficients
+        .iter()
+        .fold((0.0, 0.0), |(value, derivative), c| {
+            (value * x + c, derivative * x + value)
+        })
+}
+
+fn sorted_and_deduplicated(mut roots: Roots) -> Roots {
+    roots.retain(|root| root.is_finite());
+    roots.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    roots.dedup_by(|a, b| (*a - *b).abs() < 1e-9);
+    roots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_roots(roots: Roots, expected: &[f64]) {
+        assert_eq!(roots.len(), expected.len(), "roots were {roots:?}");
+        for (root, expected) in roots.iter().zip(expected) {
+            assert!((root - expected).abs() < 1e-9, "roots were {roots:?}");
+        }
+    }
+
+    #[test]
+    fn quadratic_keeps_precision_for_small_roots() {
+        // roots 1e-8 and 1e8, where the textbook formula loses the small one
+        assert_roots(solve_quadratic(1.0, -(1e8 + 1e-8), 1.0), &[1e-8, 1e8]);
+    }
+
+    #[test]
+    fn cubic_with_one_real_root() {
+        // (x - 2)(x² + 1)
+        assert_roots(solve_cubic(1.0, -2.0, 1.0, -2.0), &[2.0]);
+    }
+
+    #[test]
+    fn quartic_special_cases() {
+        // biquadratic (x² - 1)(x² - 4)
+        assert_roots(
+            solve_quartic(1.0, 0.0, -5.0, 0.0, 4.0),
+            &[-2.0, -1.0, 1.0, 2.0],
+        );
+        // x·(x - 1)(x + 2)(x - 3)
+        assert_roots(
+            solve_quartic(1.0, -2.0, -5.0, 6.0, 0.0),
+            &[-2.0, 0.0, 1.0, 3.0],
+        );
+        // (x² + 1)(x² + 4) has no real roots
+        assert_roots(solve_quartic(1.0, 0.0, 5.0, 0.0, 4.0), &[]);
+        // two real roots, (x - 1)(x - 5)(x² + 1)
+        assert_roots(solve_quartic(1.0, -6.0, 6.0, -6.0, 5.0), &[1.0, 5.0]);
+    }
+
+    #[test]
+    fn quartic_with_widely_spread_roots() {
+        // (x - 0.01)(x - 0.02)(x - 100)(x - 200)
+        let roots = [0.01, 0.02, 100.0, 200.0];
+        let (r1, r2, r3, r4) = (roots[0], roots[1], roots[2], roots[3]);
+        let b = -(r1 + r2 + r3 + r4);
+        let c = r1 * r2 + r1 * r3 + r1 * r4 + r2 * r3 + r2 * r4 + r3 * r4;
+        let d = -(r1 * r2 * r3 + r1 * r2 * r4 + r1 * r3 * r4 + r2 * r3 * r4);
+        let e = r1 * r2 * r3 * r4;
+        assert_roots(solve_quartic(1.0, b, c, d, e), &roots);
+    }
+}
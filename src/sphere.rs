@@ -1,14 +1,16 @@
 use super::ray::Ray;
 use crate::{
-    body::{Body, Intersectable, IntoBody},
+    body::{Body, Hits, Intersectable, IntoBody},
+    encoding::encode_fields,
     material::{Material, Phong},
     matrix::Matrix,
+    transformation::Transformation,
     tuple::Tuple,
 };
 
 #[derive(Debug, Clone)]
 pub struct Sphere {
-    transform: Matrix<4>,
+    transform: Transformation,
     material: Material,
 }
 
@@ -21,13 +23,13 @@ impl PartialEq for Sphere {
 impl Sphere {
     pub fn new(transform: Matrix<4>, material: Material) -> Self {
         Self {
-            transform,
+            transform: transform.into(),
             material,
         }
     }
 
     pub fn with_transform(mut self, t: Matrix<4>) -> Self {
-        self.transform = t;
+        self.transform.set(t);
         self
     }
 
@@ -46,15 +48,15 @@ impl Intersectable for Sphere {
         &mut self.material
     }
 
-    fn transform(&self) -> Matrix<4> {
-        self.transform
+    fn transformation(&self) -> &Transformation {
+        &self.transform
     }
 
-    fn transform_mut(&mut self) -> &mut Matrix<4_usize> {
+    fn transform_mut(&mut self) -> &mut Transformation {
         &mut self.transform
     }
 
-    fn intersect_in_object_space(&self, object_space_ray: &Ray) -> Vec<f64> {
+    fn intersect_in_object_space(&self, object_space_ray: &Ray) -> Hits {
         let sphere_to_ray = object_space_ray.origin - Tuple::Point(0.0, 0.0, 0.0);
 
         let a = object_space_ray.direction.dot(&object_space_ray.direction);
@@ -65,7 +67,7 @@ impl Intersectable for Sphere {
         let d1 = (-b - disc.sqrt()) / (2.0 * a);
         let d2 = (-b + disc.sqrt()) / (2.0 * a);
 
-        let mut ts = vec![];
+        let mut ts = Hits::new();
         if !d1.is_nan() {
             ts.push(d1);
         }
@@ -95,8 +97,13 @@ impl IntoBody for Sphere {
 impl Default for Sphere {
     fn default() -> Self {
         Self {
-            transform: Matrix::Identity(),
+            transform: Transformation::default(),
             material: Material::Phong(Phong::default()),
         }
     }
 }
+
+encode_fields!(Sphere {
+    transform,
+    material
+});
@@ -0,0 +1,298 @@
+use crate::body::Body;
+use std::{
+    cell::Cell,
+    collections::BTreeMap,
+    fmt::{Display, Formatter},
+    time::Duration,
+};
+
+const BODY_KINDS: [&str; 11] = [
+    "sphere",
+    "plane",
+    "cube",
+    "cylinder",
+    "double cone",
+    "torus",
+    "cone",
+    "disk",
+    "sdf",
+    "quadric",
+    "heightfield",
+];
+
+fn kind_index(body: &Body) -> usize {
+    match body {
+        Body::Sphere(_) => 0,
+        Body::Plane(_) => 1,
+        Body::Cube(_) => 2,
+        Body::Cylinder(_) => 3,
+        Body::DoubleCone(_) => 4,
+        Body::Torus(_) => 5,
+        Body::Cone(_) => 6,
+        Body::Disk(_) => 7,
+        Body::Sdf(_) => 8,
+        Body::Quadric(_) => 9,
+        Body::Heightfield(_) => 10,
+    }
+}
+
+/// What a traced ray was for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum RayKind {
+    Primary,
+    Shadow,
+    Reflection,
+    Refraction,
+}
+
+/// Counts gathered while rendering, e.g. by ```Camera::render_with_stats```.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RenderStats {
+    pub primary_rays: u64,
+    pub shadow_rays: u64,
+    pub reflection_rays: u64,
+    pub refraction_rays: u64,
+    /// Ray-body intersection tests by kind of body, shadow tests included.
+    pub intersection_tests: BTreeMap<&'static str, u64>,
+    /// Groups a ray was tested against. Groups are the only hierarchy in a scene, so these
+    /// are the node visits of a bounding volume hierarchy.
+    pub group_visits: u64,
+    /// Longest chain of reflected and refracted rays, 0 when only primary rays hit.
+    pub max_depth: usize,
+    /// Time spent in each phase in the order they first ran, summed over every thread
+    /// that worked on it.
+    pub phases: Vec<(&'static str, Duration)>,
+}
+
+impl RenderStats {
+    pub fn total_rays(&self) -> u64 {
+        self.primary_rays + self.shadow_rays + self.reflection_rays + self.refraction_rays
+    }
+
+    pub fn merge(&mut self, other: &RenderStats) {
+        self.primary_rays += other.primary_rays;
+        self.shadow_rays += other.shadow_rays;
+        self.reflection_rays += other.reflection_rays;
+        self.refraction_rays += other.refraction_rays;
+        for (kind, tests) in other.intersection_tests.iter() {
+            *self.intersection_tests.entry(kind).or_default() += tests;
+        }
+        self.group_visits += other.group_visits;
+        self.max_depth = self.max_depth.max(other.max_depth);
+        for (phase, time) in other.phases.iter() {
+            match self.phases.iter_mut().find(|(p, _)| p == phase) {
+                Some((_, total)) => *total += *time,
+                None => self.phases.push((phase, *time)),
+            }
+        }
+    }
+}
+
+impl Display for RenderStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "rays:               {}", self.total_rays())?;
+        writeln!(f, "  primary:          {}", self.primary_rays)?;
+        writeln!(f, "  shadow:           {}", self.shadow_rays)?;
+        writeln!(f, "  reflection:       {}", self.reflection_rays)?;
+        writeln!(f, "  refraction:       {}", self.refraction_rays)?;
+        writeln!(
+            f,
+            "intersection tests: {}",
+            self.intersection_tests.values().sum::<u64>()
+        )?;
+        for (kind, tests) in self.intersection_tests.iter() {
+            writeln!(f, "  {:<16}  {tests}", format!("{kind}:"))?;
+        }
+        writeln!(f, "group visits:       {}", self.group_visits)?;
+        writeln!(f, "max depth:          {}", self.max_depth)?;
+        write!(f, "time:")?;
+        for (phase, time) in self.phases.iter() {
+            write!(
+                f,
+                "\n  {:<16}  {} ms",
+                format!("{phase}:"),
+                time.as_millis()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// Every thread counts into its own cells, so tracing never contends on shared counters.
+struct Counters {
+    rays: [Cell<u64>; 4],
+    intersection_tests: [Cell<u64>; BODY_KINDS.len()],
+    group_visits: Cell<u64>,
+    max_depth: Cell<usize>,
+}
+
+thread_local! {
+    static COUNTERS: Counters = const {
+        Counters {
+            rays: [const { Cell::new(0) }; 4],
+            intersection_tests: [const { Cell::new(0) }; BODY_KINDS.len()],
+            group_visits: Cell::new(0),
+            max_depth: Cell::new(0),
+        }
+    };
+}
+
+fn bump(cell: &Cell<u64>) {
+    cell.set(cell.get() + 1);
+}
+
+pub(crate) fn count_ray(kind: RayKind) {
+    COUNTERS.with(|counters| bump(&counters.rays[kind as usize]));
+}
+
+pub(crate) fn count_intersection_test(body: &Body) {
+    COUNTERS.with(|counters| bump(&counters.intersection_tests[kind_index(body)]));
+}
+
+pub(crate) fn count_group_visit() {
+    COUNTERS.with(|counters| bump(&counters.group_visits));
+}
+
+pub(crate) fn reach_depth(depth: usize) {
+    COUNTERS.with(|counters| {
+        if depth > counters.max_depth.get() {
+            counters.max_depth.set(depth);
+        }
+    });
+}
+
+/// Reads and resets the counters of this thread.
+fn take() -> RenderStats {
+    COUNTERS.with(|counters| {
+        let [primary_rays, shadow_rays, reflection_rays, refraction_rays] =
+            counters.rays.each_ref().map(Cell::take);
+        RenderStats {
+            primary_rays,
+            shadow_rays,
+            reflection_rays,
+            refraction_rays,
+            intersection_tests: BODY_KINDS
+                .iter()
+                .zip(counters.intersection_tests.iter())
+                .map(|(kind, tests)| (*kind, tests.take()))
+                .filter(|(_, tests)| *tests > 0)
+                .collect(),
+            group_visits: counters.group_visits.take(),
+            max_depth: counters.max_depth.take(),
+            phases: vec![],
+        }
+    })
+}
+
+/// Adds ```stats``` back onto the counters of this thread.
+fn restore(stats: &RenderStats) {
+    COUNTERS.with(|counters| {
+        let rays = [
+            stats.primary_rays,
+            stats.shadow_rays,
+            stats.reflection_rays,
+            stats.refraction_rays,
+        ];
+        for (cell, count) in counters.rays.iter().zip(rays) {
+            cell.set(cell.get() + count);
+        }
+        for (kind, tests) in stats.intersection_tests.iter() {
+            let cell =
+                &counters.intersection_tests[BODY_KINDS.iter().position(|k| k == kind).unwrap()];
+            cell.set(cell.get() + tests);
+        }
+        counters
+            .group_visits
+            .set(counters.group_visits.get() + stats.group_visits);
+    });
+    reach_depth(stats.max_depth);
+}
+
+/// Runs ```f``` and returns what it traced on this thread. Counting is always on and costs
+/// a few increments per ray, measuring only picks out the counts made during ```f```.
+/// ```
+/// use raytracer_rust::ray::Ray;
+/// use raytracer_rust::stats::measure;
+/// use raytracer_rust::tuple::Tuple;
+/// use raytracer_rust::world::World;
+///
+/// let world = World::default_from_book();
+/// let ray = Ray::new(Tuple::Point(0, 0, -5), Tuple::Vector(0, 0, 1));
+/// let (_, stats) = measure(|| world.color_at(ray));
+/// // both spheres are tested by the camera ray and again by the shadow ray
+/// assert_eq!(stats.intersection_tests["sphere"], 4);
+/// assert_eq!(stats.shadow_rays, 1);
+/// ```
+pub fn measure<R>(f: impl FnOnce() -> R) -> (R, RenderStats) {
+    let outer = take();
+    let result = f();
+    let stats = take();
+    restore(&outer);
+    restore(&stats);
+    (result, stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        color::Color,
+        material::{Material, Phong},
+        matrix::Matrix,
+        plane::Plane,
+        point_light::PointLight,
+        ray::Ray,
+        tuple::Tuple,
+        world::World,
+    };
+
+    #[test]
+    fn counts_reflections_between_parallel_mirrors() {
+        let mirror = Material::Phong(Phong {
+            reflectiveness: 1.0,
+            ..Default::default()
+        });
+        let floor = Plane::new(Matrix::Translation(0.0, -1.0, 0.0), mirror.clone());
+        let ceiling = Plane::new(Matrix::Translation(0.0, 1.0, 0.0), mirror);
+        let world = World::new(
+            vec![PointLight::new(Tuple::Point(0, 0, 0), Color::WHITE())],
+            vec![floor.into(), ceiling.into()],
+            vec![],
+            5,
+        );
+        let ray = Ray::new(Tuple::Point(0, 0, 0), Tuple::Vector(0, 1, 0));
+        let (_, stats) = measure(|| {
+            let (_, inner) = measure(|| world.color_at(ray));
+            assert_eq!(inner.reflection_rays, 5);
+            world.color_at(ray)
+        });
+        assert_eq!(stats.reflection_rays, 10);
+        assert_eq!(stats.max_depth, 5);
+        assert_eq!(stats.refraction_rays, 0);
+        assert!(stats.shadow_rays >= 12);
+        assert_eq!(
+            stats.intersection_tests.keys().copied().collect::<Vec<_>>(),
+            ["plane"]
+        );
+
+        let mut total = stats.clone();
+        total.phases = vec![("trace", Duration::from_millis(3))];
+        total.merge(&stats);
+        total.merge(&RenderStats {
+            phases: vec![
+                ("assemble", Duration::from_millis(1)),
+                ("trace", Duration::from_millis(2)),
+            ],
+            ..Default::default()
+        });
+        assert_eq!(total.total_rays(), 2 * stats.total_rays());
+        assert_eq!(total.max_depth, 5);
+        assert_eq!(
+            total.phases,
+            [
+                ("trace", Duration::from_millis(5)),
+                ("assemble", Duration::from_millis(1))
+            ]
+        );
+    }
+}
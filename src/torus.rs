@@ -0,0 +1,244 @@
+use crate::{
+    body::{Body, Hits, Intersectable, IntoBody},
+    bounds::BoundingBox,
+    encoding::encode_fields,
+    material::{Material, Phong},
+    matrix::Matrix,
+    ray::Ray,
+    solver::solve_quartic,
+    transformation::Transformation,
+    tuple::Tuple,
+};
+
+/// Ring around the y axis: a circle of ```minor_radius``` swept around a circle of
+/// ```major_radius``` in the xz plane.
+#[derive(Clone, Debug)]
+pub struct Torus {
+    pub transform: Transformation,
+    pub material: Material,
+    pub major_radius: f64,
+    pub minor_radius: f64,
+}
+
+impl PartialEq for Torus {
+    fn eq(&self, other: &Self) -> bool {
+        self.transform == other.transform
+            && self.material == other.material
+            && self.major_radius == other.major_radius
+            && self.minor_radius == other.minor_radius
+    }
+}
+
+impl Torus {
+    pub fn new(
+        transform: Matrix<4>,
+        material: Material,
+        major_radius: f64,
+        minor_radius: f64,
+    ) -> Self {
+        Self {
+            transform: transform.into(),
+            material,
+            major_radius,
+            minor_radius,
+        }
+    }
+
+    pub fn with_transform(mut self, t: Matrix<4>) -> Self {
+        self.transform.set(t);
+        self
+    }
+
+    pub fn with_material(mut self, m: Material) -> Self {
+        self.material = m;
+        self
+    }
+
+    /// Box around the torus in object space.
+    pub fn bounds(&self) -> BoundingBox {
+        let outer = self.major_radius + self.minor_radius;
+        BoundingBox::new(
+            Tuple::Point(-outer, -self.minor_radius, -outer),
+            Tuple::Point(outer, self.minor_radius, outer),
+        )
+    }
+}
+
+impl Default for Torus {
+    fn default() -> Self {
+        Self::new(
+            Matrix::Identity(),
+            Material::Phong(Phong::default()),
+            1.0,
+            0.25,
+        )
+    }
+}
+
+impl Intersectable for Torus {
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn transformation(&self) -> &Transformation {
+        &self.transform
+    }
+
+    fn transform_mut(&mut self) -> &mut Transformation {
+        &mut self.transform
+    }
+
+    fn intersect_in_object_space(&self, object_space_ray: &Ray) -> Hits {
+        let Some((t_enter, t_leave)) = self.bounds().intersection_range(object_space_ray) else {
+            return Hits::new();
+        };
+        if t_leave <= object_space_ray.t_min || t_enter >= object_space_ray.t_max {
+            return Hits::new();
+        }
+        // The quartic is badly conditioned far from the torus, so solve it with a unit
+        // direction from where the ray enters the bounding box and convert back after.
+        let scale = object_space_ray.direction.magnitude();
+        let direction = object_space_ray.direction * (1.0 / scale);
+        let start = t_enter * scale;
+        let origin = object_space_ray.origin + direction * start;
+
+        let r2 = self.major_radius.powi(2);
+        let (ox, oy, oz) = (origin.x, origin.y, origin.z);
+        let (dx, dy, dz) = (direction.x, direction.y, direction.z);
+        let e = ox * ox + oy * oy + oz * oz + r2 - self.minor_radius.powi(2);
+        let f = ox * dx + oy * dy + oz * dz;
+
+        // (|p|² + R² - r²)² = 4R²(x² + z²) with p = origin + t·direction
+        solve_quartic(
+            1.0,
+            4.0 * f,
+            2.0 * e + 4.0 * f * f - 4.0 * r2 * (dx * dx + dz * dz),
+            4.0 * f * e - 8.0 * r2 * (ox * dx + oz * dz),
+            e * e - 4.0 * r2 * (ox * ox + oz * oz),
+        )
+        .into_iter()
+        .map(|t| (t + start) / scale)
+        .collect()
+    }
+
+    fn normal_at_in_object_space(&self, object_space_point: Tuple) -> Tuple {
+        // points away from the nearest point on the ring through the middle of the tube
+        let distance_from_axis =
+            (object_space_point.x.powi(2) + object_space_point.z.powi(2)).sqrt();
+        let k = if distance_from_axis == 0.0 {
+            0.0
+        } else {
+            self.major_radius / distance_from_axis
+        };
+        Tuple::Vector(
+            object_space_point.x * (1.0 - k),
+            object_space_point.y,
+            object_space_point.z * (1.0 - k),
+        )
+        .normalize()
+    }
+}
+
+impl From<Torus> for Body {
+    fn from(t: Torus) -> Self {
+        Body::Torus(t)
+    }
+}
+
+impl IntoBody for Torus {
+    fn into_body(&self) -> Body {
+        Body::Torus(self.clone())
+    }
+}
+
+encode_fields!(Torus {
+    transform,
+    material,
+    major_radius,
+    minor_radius
+});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_distances(actual: Hits, expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len(), "distances were {actual:?}");
+        for (t, expected) in actual.iter().zip(expected) {
+            assert!((t - expected).abs() < 1e-6, "distances were {actual:?}");
+        }
+    }
+
+    #[test]
+    fn ray_through_both_sides_of_the_ring() {
+        let torus = Torus::default();
+        let ray = Ray::new(Tuple::Point(-5.0, 0.0, 0.0), Tuple::Vector(1.0, 0.0, 0.0));
+        assert_distances(
+            torus.intersect_in_object_space(&ray),
+            &[3.75, 4.25, 5.75, 6.25],
+        );
+    }
+
+    #[test]
+    fn ray_through_the_hole_misses() {
+        let torus = Torus::default();
+        let ray = Ray::new(Tuple::Point(0.0, 5.0, 0.0), Tuple::Vector(0.0, -1.0, 0.0));
+        assert!(torus.intersect_in_object_space(&ray).is_empty());
+        let ray = Ray::new(Tuple::Point(3.0, 5.0, 0.0), Tuple::Vector(0.0, -1.0, 0.0));
+        assert!(torus.intersect_in_object_space(&ray).is_empty());
+    }
+
+    #[test]
+    fn ray_down_through_the_tube() {
+        let torus = Torus::new(
+            Matrix::Identity(),
+            Material::Phong(Phong::default()),
+            2.0,
+            0.5,
+        );
+        let ray = Ray::new(Tuple::Point(0.0, 10.0, 2.0), Tuple::Vector(0.0, -2.0, 0.0));
+        assert_distances(torus.intersect_in_object_space(&ray), &[4.75, 5.25]);
+    }
+
+    #[test]
+    fn ray_starting_inside_the_tube() {
+        let torus = Torus::default();
+        let ray = Ray::new(Tuple::Point(1.0, 0.0, 0.0), Tuple::Vector(1.0, 0.0, 0.0));
+        assert_distances(
+            torus.intersect_in_object_space(&ray),
+            &[-2.25, -1.75, -0.25, 0.25],
+        );
+    }
+
+    #[test]
+    fn transformed_torus_intersects_in_world_space() {
+        let torus = Torus::default().with_transform(Matrix::Scaling(2.0, 2.0, 2.0));
+        let ray = Ray::new(Tuple::Point(-10.0, 0.0, 0.0), Tuple::Vector(1.0, 0.0, 0.0));
+        let torus = Body::from(torus);
+        let xs = torus.intersect(&ray);
+        assert_eq!(xs.count(), 4);
+        assert!((xs[0].t - 7.5).abs() < 1e-6);
+        assert!((xs[3].t - 12.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn normals_point_out_of_the_tube() {
+        let torus = Torus::default();
+        assert_eq!(
+            torus.normal_at_in_object_space(Tuple::Point(1.25, 0.0, 0.0)),
+            Tuple::Vector(1.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            torus.normal_at_in_object_space(Tuple::Point(0.0, 0.0, -0.75)),
+            Tuple::Vector(0.0, 0.0, 1.0)
+        );
+        assert_eq!(
+            torus.normal_at_in_object_space(Tuple::Point(0.0, 0.25, 1.0)),
+            Tuple::Vector(0.0, 1.0, 0.0)
+        );
+    }
+}
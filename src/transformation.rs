@@ -0,0 +1,103 @@
+use crate::{encoding::Encode, matrix::Matrix};
+
+/// A transform together with its inverse and inverse transpose, computed once when the
+/// transform is set, so rays, normals and pattern lookups never have to invert on the fly.
+/// ```
+/// use raytracer_rust::matrix::Matrix;
+/// use raytracer_rust::transformation::Transformation;
+/// use raytracer_rust::tuple::Tuple;
+/// let mut t = Transformation::new(Matrix::Scaling(2.0, 2.0, 2.0));
+/// assert_eq!(t.inverse() * Tuple::Point(2, 4, 6), Tuple::Point(1, 2, 3));
+/// t.set(Matrix::Translation(1.0, 0.0, 0.0));
+/// assert_eq!(t.inverse() * Tuple::Point(2, 4, 6), Tuple::Point(1, 4, 6));
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct Transformation {
+    matrix: Matrix<4>,
+    inverse: Matrix<4>,
+    inverse_transpose: Matrix<4>,
+}
+
+impl Transformation {
+    pub fn new(matrix: Matrix<4>) -> Self {
+        let inverse = matrix.inverse();
+        Self {
+            matrix,
+            inverse,
+            inverse_transpose: inverse.transpose(),
+        }
+    }
+
+    /// Replaces the transform, recomputing the cached inverses.
+    pub fn set(&mut self, matrix: Matrix<4>) {
+        *self = Self::new(matrix);
+    }
+
+    pub fn matrix(&self) -> Matrix<4> {
+        self.matrix
+    }
+
+    pub fn inverse(&self) -> Matrix<4> {
+        self.inverse
+    }
+
+    /// Takes object space normals to world space.
+    pub fn inverse_transpose(&self) -> Matrix<4> {
+        self.inverse_transpose
+    }
+}
+
+impl Default for Transformation {
+    fn default() -> Self {
+        Self {
+            matrix: Matrix::Identity(),
+            inverse: Matrix::Identity(),
+            inverse_transpose: Matrix::Identity(),
+        }
+    }
+}
+
+impl PartialEq for Transformation {
+    fn eq(&self, other: &Self) -> bool {
+        self.matrix == other.matrix
+    }
+}
+
+impl PartialEq<Matrix<4>> for Transformation {
+    fn eq(&self, other: &Matrix<4>) -> bool {
+        self.matrix == *other
+    }
+}
+
+impl From<Matrix<4>> for Transformation {
+    fn from(matrix: Matrix<4>) -> Self {
+        Self::new(matrix)
+    }
+}
+
+/// Only the matrix is sent, the inverses are recomputed on decoding.
+impl Encode for Transformation {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.matrix.encode(out);
+    }
+
+    fn decode(bytes: &mut &[u8]) -> std::io::Result<Self> {
+        Ok(Self::new(Matrix::decode(bytes)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cached_inverses_follow_the_matrix() {
+        let m = Matrix::Translation(1.0, 2.0, 3.0) * Matrix::Scaling(2.0, 1.0, 0.5);
+        let mut t = Transformation::default();
+        assert_eq!(t.inverse(), Matrix::Identity());
+        t.set(m);
+        assert_eq!(t, m);
+        assert_eq!(t.inverse(), m.inverse());
+        assert_eq!(t.inverse_transpose(), m.inverse().transpose());
+    }
+}
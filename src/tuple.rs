@@ -1,4 +1,4 @@
-use crate::consts::EPSILON;
+use crate::{consts::EPSILON, encoding::Encode};
 use std::{
     fmt::Display,
     ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign},
@@ -268,3 +268,14 @@ impl Display for Tuple {
         write!(f, "{} {} {} {}", self.x, self.y, self.z, self.w)
     }
 }
+
+impl Encode for Tuple {
+    fn encode(&self, out: &mut Vec<u8>) {
+        [self.x, self.y, self.z, self.w].encode(out);
+    }
+
+    fn decode(bytes: &mut &[u8]) -> std::io::Result<Self> {
+        let [x, y, z, w] = <[f64; 4]>::decode(bytes)?;
+        Ok(Self { x, y, z, w })
+    }
+}
@@ -0,0 +1,514 @@
+use crate::{
+    canvas::{Canvas, Sized},
+    color::Color,
+    consts::PI,
+    encoding::{encode_fields, encode_units, encode_variants},
+    matrix::Matrix,
+    max,
+    pattern::Stencil,
+    transformation::Transformation,
+    tuple::Tuple,
+};
+use std::sync::Arc;
+
+pub trait UvStencil {
+    fn color_at_uv(&self, u: f64, v: f64) -> Color;
+}
+
+/// Turns a point in pattern space into (u, v) coordinates, both in [0, 1]. The spherical
+/// mapping reaches v = 1 at the north pole, where image textures read their top row.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UvMapping {
+    Spherical,
+    Planar,
+    Cylindrical,
+    Cube,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CubeFace {
+    Left,
+    Right,
+    Front,
+    Back,
+    Up,
+    Down,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum UvPattern {
+    Checkers(UvCheckers),
+    AlignCheck(UvAlignCheck),
+    Image(ImageTexture),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UvCheckers {
+    width: f64,
+    height: f64,
+    color_a: Color,
+    color_b: Color,
+}
+
+/// Single colored square with a differently colored corner in each quadrant,
+/// handy to check the orientation of a mapping.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UvAlignCheck {
+    main: Color,
+    upper_left: Color,
+    upper_right: Color,
+    bottom_left: Color,
+    bottom_right: Color,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextureFilter {
+    Nearest,
+    Bilinear,
+}
+
+/// How texels are looked up once (u, v) leaves the [0, 1) range.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WrapMode {
+    Repeat,
+    Clamp,
+    Mirror,
+}
+
+/// An image sampled in UV space, v = 0 being the bottom row of the image.
+/// The image is shared, so cloning a texture is cheap.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageTexture {
+    image: Arc<Canvas>,
+    filter: TextureFilter,
+    wrap: WrapMode,
+    u_scale: f64,
+    v_scale: f64,
+}
+
+/// Wraps a 2D pattern around a body using one of the ```UvMapping```s.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextureMap {
+    uv_pattern: UvPattern,
+    mapping: UvMapping,
+    pub transform: Transformation,
+}
+
+impl UvMapping {
+    /// ```
+    /// use raytracer_rust::uv_pattern::UvMapping;
+    /// use raytracer_rust::tuple::Tuple;
+    /// assert_eq!(UvMapping::Spherical.map(Tuple::Point(0.0, 0.0, -1.0)), (0.0, 0.5));
+    /// assert_eq!(UvMapping::Spherical.map(Tuple::Point(1.0, 0.0, 0.0)), (0.25, 0.5));
+    /// assert_eq!(UvMapping::Spherical.map(Tuple::Point(0.0, 1.0, 0.0)), (0.5, 1.0));
+    /// assert_eq!(UvMapping::Planar.map(Tuple::Point(0.25, 0.0, -1.75)), (0.25, 0.25));
+    /// assert_eq!(UvMapping::Cylindrical.map(Tuple::Point(0.0, 0.5, -1.0)), (0.0, 0.5));
+    /// ```
+    pub fn map(&self, point: Tuple) -> (f64, f64) {
+        match self {
+            UvMapping::Spherical => {
+                let theta = point.x.atan2(point.z);
+                let radius = (point.x.powi(2) + point.y.powi(2) + point.z.powi(2)).sqrt();
+                let phi = (point.y / radius).acos();
+                let raw_u = theta / (2.0 * PI);
+                (1.0 - (raw_u + 0.5), 1.0 - phi / PI)
+            }
+            UvMapping::Planar => (point.x - point.x.floor(), point.z - point.z.floor()),
+            UvMapping::Cylindrical => {
+                let theta = point.x.atan2(point.z);
+                let raw_u = theta / (2.0 * PI);
+                (1.0 - (raw_u + 0.5), point.y - point.y.floor())
+            }
+            UvMapping::Cube => CubeFace::of(point).uv(point),
+        }
+    }
+}
+
+impl CubeFace {
+    /// Face of the unit cube that the point lies on (or is closest to).
+    pub fn of(point: Tuple) -> Self {
+        let coord = max!(point.x.abs(), point.y.abs(), point.z.abs());
+        if coord == point.x {
+            CubeFace::Right
+        } else if coord == -point.x {
+            CubeFace::Left
+        } else if coord == point.y {
+            CubeFace::Up
+        } else if coord == -point.y {
+            CubeFace::Down
+        } else if coord == point.z {
+            CubeFace::Front
+        } else {
+            CubeFace::Back
+        }
+    }
+
+    /// ```
+    /// use raytracer_rust::uv_pattern::CubeFace;
+    /// use raytracer_rust::tuple::Tuple;
+    /// assert_eq!(CubeFace::Front.uv(Tuple::Point(-0.5, 0.5, 1.0)), (0.25, 0.75));
+    /// assert_eq!(CubeFace::Back.uv(Tuple::Point(0.5, 0.5, -1.0)), (0.25, 0.75));
+    /// assert_eq!(CubeFace::Left.uv(Tuple::Point(-1.0, 0.5, -0.5)), (0.25, 0.75));
+    /// assert_eq!(CubeFace::Right.uv(Tuple::Point(1.0, 0.5, 0.5)), (0.25, 0.75));
+    /// assert_eq!(CubeFace::Up.uv(Tuple::Point(-0.5, 1.0, -0.5)), (0.25, 0.75));
+    /// assert_eq!(CubeFace::Down.uv(Tuple::Point(-0.5, -1.0, 0.5)), (0.25, 0.75));
+    /// ```
+    pub fn uv(&self, point: Tuple) -> (f64, f64) {
+        let wrap = |a: f64| (a + 1.0).rem_euclid(2.0) / 2.0;
+        match self {
+            CubeFace::Front => (wrap(point.x), wrap(point.y)),
+            CubeFace::Back => (wrap(-point.x), wrap(point.y)),
+            CubeFace::Left => (wrap(point.z), wrap(point.y)),
+            CubeFace::Right => (wrap(-point.z), wrap(point.y)),
+            CubeFace::Up => (wrap(point.x), wrap(-point.z)),
+            CubeFace::Down => (wrap(point.x), wrap(point.z)),
+        }
+    }
+}
+
+impl UvCheckers {
+    pub fn new(width: f64, height: f64, color_a: Color, color_b: Color) -> Self {
+        Self {
+            width,
+            height,
+            color_a,
+            color_b,
+        }
+    }
+}
+
+impl UvAlignCheck {
+    pub fn new(
+        main: Color,
+        upper_left: Color,
+        upper_right: Color,
+        bottom_left: Color,
+        bottom_right: Color,
+    ) -> Self {
+        Self {
+            main,
+            upper_left,
+            upper_right,
+            bottom_left,
+            bottom_right,
+        }
+    }
+}
+
+impl ImageTexture {
+    pub fn new(image: Canvas, filter: TextureFilter, wrap: WrapMode) -> Self {
+        Self {
+            image: Arc::new(image),
+            filter,
+            wrap,
+            u_scale: 1.0,
+            v_scale: 1.0,
+        }
+    }
+
+    pub fn load_ppm<T: AsRef<str>>(
+        load_from: T,
+        filter: TextureFilter,
+        wrap: WrapMode,
+    ) -> std::io::Result<Self> {
+        Ok(Self::new(Canvas::load_ppm(load_from)?, filter, wrap))
+    }
+
+    pub fn image(&self) -> &Canvas {
+        &self.image
+    }
+
+    /// Number of times the image is laid across the [0, 1) range along u and v.
+    pub fn with_scale(mut self, u_scale: f64, v_scale: f64) -> Self {
+        self.u_scale = u_scale;
+        self.v_scale = v_scale;
+        self
+    }
+
+    fn texel(&self, x: isize, y: isize) -> Color {
+        let x = self.wrap.apply(x, self.image.width());
+        let y = self.wrap.apply(y, self.image.height());
+        self.image.color_at_pixel(x, y)
+    }
+}
+
+impl WrapMode {
+    fn apply(&self, index: isize, size: usize) -> usize {
+        let size = size as isize;
+        let index = match self {
+            WrapMode::Repeat => index.rem_euclid(size),
+            WrapMode::Clamp => index.clamp(0, size - 1),
+            WrapMode::Mirror => {
+                let index = index.rem_euclid(2 * size);
+                if index < size {
+                    index
+                } else {
+                    2 * size - 1 - index
+                }
+            }
+        };
+        index as usize
+    }
+}
+
+impl TextureMap {
+    pub fn new(uv_pattern: UvPattern, mapping: UvMapping, transform: Matrix<4>) -> Self {
+        Self {
+            uv_pattern,
+            mapping,
+            transform: transform.into(),
+        }
+    }
+}
+
+impl UvStencil for UvPattern {
+    fn color_at_uv(&self, u: f64, v: f64) -> Color {
+        match self {
+            UvPattern::Checkers(checkers) => checkers.color_at_uv(u, v),
+            UvPattern::AlignCheck(align_check) => align_check.color_at_uv(u, v),
+            UvPattern::Image(image) => image.color_at_uv(u, v),
+        }
+    }
+}
+
+impl UvStencil for UvCheckers {
+    fn color_at_uv(&self, u: f64, v: f64) -> Color {
+        let distance = (u * self.width).floor() + (v * self.height).floor();
+        if distance as isize % 2 == 0 {
+            self.color_a
+        } else {
+            self.color_b
+        }
+    }
+}
+
+impl UvStencil for UvAlignCheck {
+    fn color_at_uv(&self, u: f64, v: f64) -> Color {
+        if v > 0.8 {
+            if u < 0.2 {
+                return self.upper_left;
+            }
+            if u > 0.8 {
+                return self.upper_right;
+            }
+        } else if v < 0.2 {
+            if u < 0.2 {
+                return self.bottom_left;
+            }
+            if u > 0.8 {
+                return self.bottom_right;
+            }
+        }
+        self.main
+    }
+}
+
+impl UvStencil for ImageTexture {
+    fn color_at_uv(&self, u: f64, v: f64) -> Color {
+        let x = u * self.u_scale * self.image.width() as f64;
+        let y = (1.0 - v * self.v_scale) * self.image.height() as f64;
+        match self.filter {
+            TextureFilter::Nearest => self.texel(x.floor() as isize, y.floor() as isize),
+            TextureFilter::Bilinear => {
+                // texel centers sit at half-integer coordinates
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (x0, y0) = (x.floor(), y.floor());
+                let (fx, fy) = (x - x0, y - y0);
+                let (x0, y0) = (x0 as isize, y0 as isize);
+                let top = self.texel(x0, y0) * (1.0 - fx) + self.texel(x0 + 1, y0) * fx;
+                let bottom = self.texel(x0, y0 + 1) * (1.0 - fx) + self.texel(x0 + 1, y0 + 1) * fx;
+                top * (1.0 - fy) + bottom * fy
+            }
+        }
+    }
+}
+
+impl Stencil for TextureMap {
+    fn color_at_in_pattern_space(&self, position: Tuple) -> Color {
+        let (u, v) = self.mapping.map(position);
+        self.uv_pattern.color_at_uv(u, v)
+    }
+
+    fn transformation(&self) -> &Transformation {
+        &self.transform
+    }
+}
+
+encode_units!(UvMapping {
+    0 => Spherical,
+    1 => Planar,
+    2 => Cylindrical,
+    3 => Cube,
+});
+encode_variants!(UvPattern {
+    0 => Checkers,
+    1 => AlignCheck,
+    2 => Image,
+});
+encode_fields!(UvCheckers {
+    width,
+    height,
+    color_a,
+    color_b
+});
+encode_fields!(UvAlignCheck {
+    main,
+    upper_left,
+    upper_right,
+    bottom_left,
+    bottom_right
+});
+encode_units!(TextureFilter { 0 => Nearest, 1 => Bilinear });
+encode_units!(WrapMode { 0 => Repeat, 1 => Clamp, 2 => Mirror });
+encode_fields!(ImageTexture {
+    image,
+    filter,
+    wrap,
+    u_scale,
+    v_scale
+});
+encode_fields!(TextureMap {
+    uv_pattern,
+    mapping,
+    transform
+});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uv_checkers_works() {
+        let checkers = UvCheckers::new(2.0, 2.0, Color::BLACK(), Color::WHITE());
+        assert_eq!(checkers.color_at_uv(0.0, 0.0), Color::BLACK());
+        assert_eq!(checkers.color_at_uv(0.5, 0.0), Color::WHITE());
+        assert_eq!(checkers.color_at_uv(0.0, 0.5), Color::WHITE());
+        assert_eq!(checkers.color_at_uv(0.5, 0.5), Color::BLACK());
+        assert_eq!(checkers.color_at_uv(1.0, 1.0), Color::BLACK());
+    }
+
+    #[test]
+    fn checkers_on_a_sphere_with_spherical_mapping() {
+        let pattern = TextureMap::new(
+            UvPattern::Checkers(UvCheckers::new(16.0, 8.0, Color::BLACK(), Color::WHITE())),
+            UvMapping::Spherical,
+            Matrix::Identity(),
+        );
+        for (point, color) in [
+            (Tuple::Point(0.4315, 0.4670, 0.7719), Color::WHITE()),
+            (Tuple::Point(-0.9654, 0.2552, -0.0534), Color::BLACK()),
+            (Tuple::Point(0.1039, 0.7090, 0.6975), Color::WHITE()),
+            (Tuple::Point(-0.4986, -0.7856, -0.3663), Color::BLACK()),
+            (Tuple::Point(-0.0317, -0.9395, 0.3411), Color::BLACK()),
+            (Tuple::Point(0.4809, -0.7721, 0.4154), Color::BLACK()),
+            (Tuple::Point(0.0285, -0.9612, -0.2745), Color::BLACK()),
+            (Tuple::Point(-0.5734, -0.2162, -0.7903), Color::WHITE()),
+            (Tuple::Point(0.7688, -0.1470, 0.6223), Color::BLACK()),
+            (Tuple::Point(-0.7652, 0.2175, 0.6060), Color::BLACK()),
+        ] {
+            assert_eq!(pattern.color_at_in_pattern_space(point), color);
+        }
+    }
+
+    #[test]
+    fn cube_face_of_a_point() {
+        assert_eq!(CubeFace::of(Tuple::Point(-1.0, 0.5, -0.25)), CubeFace::Left);
+        assert_eq!(CubeFace::of(Tuple::Point(1.1, -0.75, 0.8)), CubeFace::Right);
+        assert_eq!(CubeFace::of(Tuple::Point(0.1, 0.6, 0.9)), CubeFace::Front);
+        assert_eq!(CubeFace::of(Tuple::Point(-0.7, 0.0, -2.0)), CubeFace::Back);
+        assert_eq!(CubeFace::of(Tuple::Point(0.5, 1.0, 0.9)), CubeFace::Up);
+        assert_eq!(CubeFace::of(Tuple::Point(-0.2, -1.3, 1.1)), CubeFace::Down);
+    }
+
+    #[test]
+    fn cube_mapping_puts_align_check_upright_on_every_face() {
+        let pattern = TextureMap::new(
+            UvPattern::AlignCheck(UvAlignCheck::new(
+                Color::WHITE(),
+                Color::RED(),
+                Color::GREEN(),
+                Color::BLUE(),
+                Color::BLACK(),
+            )),
+            UvMapping::Cube,
+            Matrix::Identity(),
+        );
+        for (upper_left, bottom_right) in [
+            (Tuple::Point(-0.9, 0.9, 1.0), Tuple::Point(0.9, -0.9, 1.0)),
+            (Tuple::Point(0.9, 0.9, -1.0), Tuple::Point(-0.9, -0.9, -1.0)),
+            (Tuple::Point(-1.0, 0.9, -0.9), Tuple::Point(-1.0, -0.9, 0.9)),
+            (Tuple::Point(1.0, 0.9, 0.9), Tuple::Point(1.0, -0.9, -0.9)),
+        ] {
+            assert_eq!(pattern.color_at_in_pattern_space(upper_left), Color::RED());
+            assert_eq!(
+                pattern.color_at_in_pattern_space(bottom_right),
+                Color::BLACK()
+            );
+        }
+        assert_eq!(
+            pattern.color_at_in_pattern_space(Tuple::Point(0.0, 1.0, 0.0)),
+            Color::WHITE()
+        );
+    }
+
+    fn two_by_two_image() -> Canvas {
+        let mut image = Canvas::new(2, 2);
+        image.set_color_at_pixel(0, 0, Color::RED());
+        image.set_color_at_pixel(1, 0, Color::GREEN());
+        image.set_color_at_pixel(0, 1, Color::BLUE());
+        image.set_color_at_pixel(1, 1, Color::WHITE());
+        image
+    }
+
+    #[test]
+    fn image_texture_nearest_sampling() {
+        let texture =
+            ImageTexture::new(two_by_two_image(), TextureFilter::Nearest, WrapMode::Repeat);
+        assert_eq!(texture.color_at_uv(0.25, 0.75), Color::RED());
+        assert_eq!(texture.color_at_uv(0.75, 0.75), Color::GREEN());
+        assert_eq!(texture.color_at_uv(0.25, 0.25), Color::BLUE());
+        assert_eq!(texture.color_at_uv(0.75, 0.25), Color::WHITE());
+    }
+
+    #[test]
+    fn image_texture_bilinear_sampling() {
+        let texture =
+            ImageTexture::new(two_by_two_image(), TextureFilter::Bilinear, WrapMode::Clamp);
+        assert_eq!(texture.color_at_uv(0.25, 0.75), Color::RED());
+        assert_eq!(texture.color_at_uv(0.5, 0.75), Color::new(0.5, 0.5, 0.0));
+        assert_eq!(texture.color_at_uv(0.5, 0.5), Color::new(0.5, 0.5, 0.5));
+        // clamping keeps the edge texel beyond the border
+        assert_eq!(texture.color_at_uv(0.0, 1.0), Color::RED());
+    }
+
+    #[test]
+    fn image_texture_wrap_modes() {
+        let image = two_by_two_image();
+        let repeat = ImageTexture::new(image.clone(), TextureFilter::Nearest, WrapMode::Repeat)
+            .with_scale(2.0, 1.0);
+        let clamp = ImageTexture::new(image.clone(), TextureFilter::Nearest, WrapMode::Clamp)
+            .with_scale(2.0, 1.0);
+        let mirror =
+            ImageTexture::new(image, TextureFilter::Nearest, WrapMode::Mirror).with_scale(2.0, 1.0);
+        // u = 0.6 lands on the first texel of the second copy of the image
+        assert_eq!(repeat.color_at_uv(0.6, 0.75), Color::RED());
+        assert_eq!(clamp.color_at_uv(0.6, 0.75), Color::GREEN());
+        assert_eq!(mirror.color_at_uv(0.6, 0.75), Color::GREEN());
+        assert_eq!(mirror.color_at_uv(0.9, 0.75), Color::RED());
+    }
+
+    #[test]
+    fn align_check_corners() {
+        let main = Color::WHITE();
+        let ul = Color::RED();
+        let ur = Color::new(1.0, 1.0, 0.0);
+        let bl = Color::GREEN();
+        let br = Color::new(0.0, 1.0, 1.0);
+        let pattern = UvAlignCheck::new(main, ul, ur, bl, br);
+        assert_eq!(pattern.color_at_uv(0.5, 0.5), main);
+        assert_eq!(pattern.color_at_uv(0.1, 0.9), ul);
+        assert_eq!(pattern.color_at_uv(0.9, 0.9), ur);
+        assert_eq!(pattern.color_at_uv(0.1, 0.1), bl);
+        assert_eq!(pattern.color_at_uv(0.9, 0.1), br);
+    }
+}
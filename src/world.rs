@@ -1,15 +1,22 @@
 use crate::{
+    background::Background,
     body::{Body, Intersectable},
-    color::Color,
+    color::{Color, RGB},
     computed_intersection::ComputedIntersection,
+    consts::{EPSILON, PI, WAVELENGTH_BLUE, WAVELENGTH_GREEN, WAVELENGTH_RED},
+    encoding::encode_fields,
+    environment_light::EnvironmentLight,
     group::Group,
-    intersections::Intersections,
+    inline_vec::InlineVec,
+    intersections::{Intersections, INLINE_INTERSECTIONS},
     material::{Material, Phong, PhongLighting, Reflective, Refractive},
     matrix::Matrix,
     pattern::{Flat, Pattern},
     point_light::PointLight,
     ray::Ray,
+    sampler::Sampler,
     sphere::Sphere,
+    stats::{self, RayKind},
     tuple::Tuple,
 };
 
@@ -19,6 +26,8 @@ pub struct World {
     pub bodies: Vec<Body>,
     pub groups: Vec<Group>,
     pub reflection_limit: usize,
+    pub background: Background,
+    pub environment_light: Option<EnvironmentLight>,
 }
 
 impl World {
@@ -33,9 +42,21 @@ impl World {
             bodies,
             groups,
             reflection_limit,
+            background: Background::default(),
+            environment_light: None,
         }
     }
 
+    pub fn with_environment_light(mut self, light: EnvironmentLight) -> Self {
+        self.environment_light = Some(light);
+        self
+    }
+
+    pub fn with_background(mut self, background: Background) -> Self {
+        self.background = background;
+        self
+    }
+
     pub fn add_point_light(&mut self, l: PointLight) {
         self.point_lights.push(l);
     }
@@ -44,20 +65,57 @@ impl World {
         self.bodies.push(s);
     }
 
-    pub fn intersect(&self, r: Ray) -> Intersections {
+    /// Sorted intersections up to and including the closest hit in front of the ray.
+    /// The ray's ```t_max``` shrinks to the closest hit found so far, so bodies further
+    /// away are culled early. Hits before ```t_min``` and behind the origin are kept, since
+    /// refraction needs them to know which bodies the ray is inside of, but ```hit``` skips them.
+    pub fn intersect(&self, r: Ray) -> Intersections<'_> {
         let mut xs = Intersections::default();
-        self.bodies.iter().for_each(|s| {
-            let x = s.intersect(&r);
-            xs.extend(x);
-        });
-        self.groups.iter().for_each(|grp| {
-            let x = Group::intersect(grp, &r);
-            xs.extend(x);
-        });
+        xs.t_min = r.t_min.max(0.0);
+        let mut ray = r.with_interval(f64::NEG_INFINITY, r.t_max);
+        let shrink_to_closest = |ray: &mut Ray, xs: &Intersections, from: usize| {
+            for i in from..xs.count() {
+                if xs[i].t > xs.t_min && xs[i].t < ray.t_max {
+                    ray.t_max = xs[i].t;
+                }
+            }
+        };
+        for body in self.bodies.iter() {
+            let from = xs.count();
+            body.intersect_into(&ray, &mut xs);
+            shrink_to_closest(&mut ray, &xs, from);
+        }
+        for grp in self.groups.iter() {
+            let from = xs.count();
+            grp.intersect_into(&ray, &mut xs);
+            shrink_to_closest(&mut ray, &xs, from);
+        }
+        // bodies checked before the closest hit was known may have added hits beyond it
+        xs.retain(|i| i.t <= ray.t_max);
+        xs.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
+        xs
+    }
+
+    /// Every intersection within the ray's interval, sorted.
+    pub fn intersect_all(&self, r: Ray) -> Intersections<'_> {
+        let mut xs = Intersections::default();
+        self.bodies
+            .iter()
+            .for_each(|s| s.intersect_into(&r, &mut xs));
+        self.groups
+            .iter()
+            .for_each(|grp| grp.intersect_into(&r, &mut xs));
         xs.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
         xs
     }
 
+    /// Whether anything blocks ```ray``` within its interval.
+    /// Stops at the first blocker found instead of collecting and sorting every hit.
+    pub fn is_occluded(&self, ray: &Ray) -> bool {
+        self.bodies.iter().any(|body| body.occludes(ray))
+            || self.groups.iter().any(|grp| grp.occludes(ray))
+    }
+
     /// ```
     /// use raytracer_rust::world::World;
     /// use raytracer_rust::tuple::Tuple;
@@ -70,28 +128,48 @@ impl World {
     /// assert_eq!(c, Color::new(0.38066, 0.47583, 0.2855));
     /// ```
     pub fn color_at(&self, ray: Ray) -> Color {
-        self.color_at_with_reflection_limit(ray, self.reflection_limit)
+        self.color_at_with_reflection_limit(ray, self.reflection_limit, None)
     }
 
-    fn color_at_with_reflection_limit(&self, ray: Ray, remaining_reflections: usize) -> Color {
+    /// ```wavelength``` is set once dispersion has split the ray into one ray per color
+    /// channel, and then refraction and reflectance are computed for that wavelength only.
+    fn color_at_with_reflection_limit(
+        &self,
+        ray: Ray,
+        remaining_reflections: usize,
+        wavelength: Option<f64>,
+    ) -> Color {
+        stats::reach_depth(self.reflection_limit - remaining_reflections);
         let xs = self.intersect(ray);
         if let Some(intersection) = xs.hit() {
             let (mu_from, mu_to) = xs.get_mu_shift(intersection);
+            let (abbe_from, abbe_to) = xs.get_abbe_shift(intersection);
             let material = intersection.body.material();
-            let cs = intersection.as_computed(mu_from, mu_to);
+            let cs = intersection
+                .as_computed(mu_from, mu_to)
+                .with_abbe_numbers(abbe_from, abbe_to)
+                .with_perturbed_normal();
             let surface_color = self.surface_color_at(&cs);
-            let reflected_color = self.reflected_color_at(&cs, material, remaining_reflections);
-            let refracted_color = self.refracted_color_at(&cs, material, remaining_reflections);
-            let reflectance = cs.schlick();
+            let reflected_color =
+                self.reflected_color_at(&cs, material, remaining_reflections, wavelength);
+            let refracted_color =
+                self.refracted_color_at(&cs, material, remaining_reflections, wavelength);
             if material.transparency() != 0.0 && material.reflectiveness() != 0.0 {
+                let reflectance = match wavelength {
+                    Some(wavelength) => {
+                        let reflectance = cs.schlick_at(wavelength);
+                        Color::new(reflectance, reflectance, reflectance)
+                    }
+                    None => cs.schlick_color(),
+                };
                 surface_color
                     + reflected_color * reflectance
-                    + refracted_color * (1.0 - reflectance)
+                    + refracted_color * (Color::WHITE() - reflectance)
             } else {
                 surface_color + reflected_color + refracted_color
             }
         } else {
-            Color::BLACK()
+            self.background.color_in_direction(ray.direction)
         }
     }
 
@@ -109,37 +187,78 @@ impl World {
     /// let ray = Ray::new(Tuple::Point(0, 0, -5), Tuple::Vector(0, 0, 1));
     ///
     /// let shape = world.bodies[0].clone();
-    /// let i = Intersection::new(4.0, shape.into(), ray);
-    /// let comps = i.to_computed(0.0,0.0);
+    /// let i = Intersection::new(4.0, &shape, ray);
+    /// let comps = i.as_computed(1.0, 1.0);
     /// let c = world.surface_color_at(&comps);
     /// assert_eq!(c, Color::new(0.38066, 0.47583, 0.2855));
     ///
     /// world.point_lights = vec![PointLight::new(Tuple::Point(0.0, 0.25, 0.0), Color::new(1.0, 1.0, 1.0))];
     /// let ray = Ray::new(Tuple::Point(0, 0, 0), Tuple::Vector(0, 0, 1));
     /// let shape = world.bodies[1].clone();
-    /// let i = Intersection::new(0.5, shape.into(), ray);
-    /// let comps = i.to_computed(0.0,0.0);
+    /// let i = Intersection::new(0.5, &shape, ray);
+    /// let comps = i.as_computed(1.0, 1.0);
     /// let c = world.surface_color_at(&comps);
     /// assert_eq!(c, Color::new(0.90498, 0.90498, 0.90498));
     /// ```
     pub fn surface_color_at(&self, comps: &ComputedIntersection) -> Color {
-        assert_eq!(
-            self.point_lights.len(),
-            1,
+        assert!(
+            self.point_lights.len() == 1
+                || (self.point_lights.is_empty() && self.environment_light.is_some()),
             "please read FIXME in surface_color_at"
         );
-        let surface_color = comps.body.material().lighting(
-            // FIXME: why point_lights[0] is hard coded
-            // maybe, iterate through all point lights and add the color of each light
-            // adding might be a problem, if its sum > 1 for a color component
-            &comps.body,
-            self.point_lights[0],
+        let mut surface_color = Color::BLACK();
+        if let Some(light) = self.point_lights.first() {
+            surface_color = comps.body.material().lighting(
+                // FIXME: why point_lights[0] is hard coded
+                // maybe, iterate through all point lights and add the color of each light
+                // adding might be a problem, if its sum > 1 for a color component
+                comps.body,
+                *light,
+                comps.point,
+                comps.eyev,
+                comps.normalv,
+                self.transparency_factor(comps.over_point),
+            );
+        }
+        surface_color + self.environment_lighting(comps)
+    }
+
+    /// Monte Carlo estimate of the light arriving from the environment map.
+    /// Samples are seeded by the surface point, so a render is reproducible.
+    fn environment_lighting(&self, comps: &ComputedIntersection) -> Color {
+        let light = match &self.environment_light {
+            Some(light) => light,
+            None => return Color::BLACK(),
+        };
+        let mut sampler = Sampler::from_seeds(&[
+            comps.point.x.to_bits(),
+            comps.point.y.to_bits(),
+            comps.point.z.to_bits(),
+        ]);
+        let samples = light.samples();
+        let lights = (0..samples)
+            .filter_map(|_| light.sample(&mut sampler))
+            .filter(|sample| sample.direction.dot(&comps.normalv) > 0.0)
+            .filter_map(|sample| {
+                // transparent bodies dim the sample the same way they dim point lights
+                let shadow_ray = Ray::new(comps.over_point, sample.direction)
+                    .with_interval(EPSILON, f64::INFINITY);
+                let transparency = self.transparency_along(&shadow_ray);
+                if transparency == 0.0 {
+                    return None;
+                }
+                // dividing by π keeps a uniform white environment as bright as a white
+                // point light shining straight at the surface
+                let weight = transparency / (PI * samples as f64 * sample.pdf);
+                Some((sample.direction, sample.radiance * weight))
+            });
+        comps.body.material().lighting_from_directions(
+            comps.body,
             comps.point,
             comps.eyev,
             comps.normalv,
-            self.transparency_factor(comps.over_point),
-        );
-        surface_color
+            lights,
+        )
     }
 
     fn reflected_color_at(
@@ -147,11 +266,17 @@ impl World {
         cs: &ComputedIntersection,
         material: &Material,
         remaining_reflections: usize,
+        wavelength: Option<f64>,
     ) -> Color {
         if remaining_reflections > 0 && material.reflectiveness() != 0.0 {
-            let reflected_ray = Ray::new(cs.over_point, cs.reflectv);
-            let color =
-                self.color_at_with_reflection_limit(reflected_ray, remaining_reflections - 1);
+            let reflected_ray =
+                Ray::new(cs.over_point, cs.reflectv).with_interval(EPSILON, f64::INFINITY);
+            stats::count_ray(RayKind::Reflection);
+            let color = self.color_at_with_reflection_limit(
+                reflected_ray,
+                remaining_reflections - 1,
+                wavelength,
+            );
             color * material.reflectiveness()
         } else {
             Color::BLACK()
@@ -163,39 +288,63 @@ impl World {
         cs: &ComputedIntersection,
         material: &Material,
         remaining_reflections: usize,
+        wavelength: Option<f64>,
     ) -> Color {
         if remaining_reflections > 0 && material.transparency() != 0.0 {
-            let mu_ratio = cs.mu_from / cs.mu_to;
-            let cos_i = cs.eyev.dot(&cs.normalv);
-            let sin2_t = (mu_ratio * mu_ratio) as f64 * (1.0 - (cos_i * cos_i));
-            if sin2_t <= 1.0 {
-                // refract
-                let cos_t = (1.0 - sin2_t).sqrt();
-                let direction = cs.normalv * (mu_ratio as f64 * cos_i - cos_t) - cs.eyev * mu_ratio;
-
-                let refracted_ray = Ray::new(cs.under_point, direction);
-                let refracted_color =
-                    self.color_at_with_reflection_limit(refracted_ray, remaining_reflections - 1);
-
-                refracted_color * material.transparency()
+            let trace = |wavelength: Option<f64>| {
+                let (mu_from, mu_to) = match wavelength {
+                    Some(wavelength) => cs.mu_at(wavelength),
+                    None => (cs.mu_from, cs.mu_to),
+                };
+                self.trace_refraction(cs, mu_from / mu_to, remaining_reflections, wavelength)
+            };
+            let refracted_color = if wavelength.is_none() && cs.is_dispersive() {
+                // split into one ray per channel, each refracted for its own wavelength from
+                // here on, so nested dispersive bodies don't split again
+                let [red, green, blue] = [WAVELENGTH_RED, WAVELENGTH_GREEN, WAVELENGTH_BLUE]
+                    .map(|wavelength| trace(Some(wavelength)));
+                Color::new(red.red(), green.green(), blue.blue())
             } else {
-                // total-internal reflection
-                Color::BLACK()
-            }
+                trace(wavelength)
+            };
+            refracted_color * material.transparency()
         } else {
             Color::BLACK()
         }
     }
 
+    fn trace_refraction(
+        &self,
+        cs: &ComputedIntersection,
+        mu_ratio: f32,
+        remaining_reflections: usize,
+        wavelength: Option<f64>,
+    ) -> Color {
+        match refraction_direction(cs, mu_ratio) {
+            Some(direction) => {
+                let refracted_ray =
+                    Ray::new(cs.under_point, direction).with_interval(EPSILON, f64::INFINITY);
+                stats::count_ray(RayKind::Refraction);
+                self.color_at_with_reflection_limit(
+                    refracted_ray,
+                    remaining_reflections - 1,
+                    wavelength,
+                )
+            }
+            // total-internal reflection
+            None => Color::BLACK(),
+        }
+    }
+
     // FIXME: using "any" is not well understood.
     pub fn is_shadowed(&self, point: Tuple) -> bool {
         self.point_lights.iter().any(|light| {
             let v = light.position - point;
             let distance = v.magnitude();
             let direction = v.normalize();
-            let r = Ray::new(point, direction);
-            let intersections = self.intersect(r);
-            intersections.hit().map(|i| i.t < distance).unwrap_or(false)
+            let r = Ray::new(point, direction).with_interval(EPSILON, distance);
+            stats::count_ray(RayKind::Shadow);
+            self.is_occluded(&r)
         })
     }
 
@@ -204,20 +353,27 @@ impl World {
         let v = light.position - point;
         let distance = v.magnitude();
         let direction = v.normalize();
-        let r = Ray::new(point, direction);
-        let intersections = self.intersect(r);
-        let mut hit_objects = vec![];
-        let factor = intersections
-            .iter()
-            .filter(|i| !(i.t.is_sign_negative() || i.t > distance))
-            .fold(1.0, |acc, i| {
-                if hit_objects.contains(&i.body.material()) {
-                    acc
-                } else { 
-                    hit_objects.push(i.body.material());
-                    acc * i.body.material().transparency() as f64
-                }
-            });
+        self.transparency_along(&Ray::new(point, direction).with_interval(EPSILON, distance))
+    }
+
+    /// How much light passes along ```shadow_ray```, the product of the transparencies of
+    /// the materials it passes through.
+    fn transparency_along(&self, shadow_ray: &Ray) -> f64 {
+        stats::count_ray(RayKind::Shadow);
+        // most points see the light unobstructed, so only collect the blockers when there are any
+        if !self.is_occluded(shadow_ray) {
+            return 1.0;
+        }
+        let intersections = self.intersect_all(*shadow_ray);
+        let mut hit_objects: InlineVec<&Material, INLINE_INTERSECTIONS> = InlineVec::new();
+        let factor = intersections.iter().fold(1.0, |acc, i| {
+            if hit_objects.contains(&i.body.material()) {
+                acc
+            } else {
+                hit_objects.push(i.body.material());
+                acc * i.body.material().transparency() as f64
+            }
+        });
         factor
     }
 
@@ -242,20 +398,54 @@ impl World {
             ],
             reflection_limit: 0,
             groups: vec![],
+            background: Background::default(),
+            environment_light: None,
         }
     }
 }
 
+/// Direction of the ray refracted at ```cs``` for the ratio of refractive indices
+/// ```mu_ratio```, or ```None``` on total internal reflection.
+fn refraction_direction(cs: &ComputedIntersection, mu_ratio: f32) -> Option<Tuple> {
+    let cos_i = cs.eyev.dot(&cs.normalv);
+    let sin2_t = (mu_ratio * mu_ratio) as f64 * (1.0 - (cos_i * cos_i));
+    if sin2_t > 1.0 {
+        return None;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    Some(cs.normalv * (mu_ratio as f64 * cos_i - cos_t) - cs.eyev * mu_ratio)
+}
+
+encode_fields!(World {
+    point_lights,
+    bodies,
+    groups,
+    reflection_limit,
+    background,
+    environment_light,
+});
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::pattern::Stencil;
+    use crate::{
+        background::{EnvironmentMap, VerticalGradient},
+        canvas::Canvas,
+        cone::Cone,
+        heightfield::Heightfield,
+        pattern::Stencil,
+        plane::Plane,
+        quadric::Quadric,
+        sdf::{DistanceField, Sdf},
+        torus::Torus,
+        uv_pattern::{ImageTexture, TextureFilter, WrapMode},
+    };
 
     #[test]
     fn test_intersect_world_with_ray() {
         let w = World::default_from_book();
         let r = Ray::new(Tuple::Point(0.0, 0.0, -5.0), Tuple::Vector(0.0, 0.0, 1.0));
-        let xs = w.intersect(r);
+        let xs = w.intersect_all(r);
         assert_eq!(xs.count(), 4);
         assert_eq!(xs[0].t, 4.0);
         assert_eq!(xs[1].t, 4.5);
@@ -263,6 +453,23 @@ mod tests {
         assert_eq!(xs[3].t, 6.0);
     }
 
+    #[test]
+    fn intersect_stops_at_the_closest_hit() {
+        let w = World::default_from_book();
+        let r = Ray::new(Tuple::Point(0.0, 0.0, -5.0), Tuple::Vector(0.0, 0.0, 1.0));
+        let ts: Vec<f64> = w.intersect(r).iter().map(|i| i.t).collect();
+        assert_eq!(ts, vec![4.0]);
+        // surfaces behind the origin stay, so the ray knows it is inside both spheres
+        let r = Ray::new(Tuple::Point(0.0, 0.0, 0.0), Tuple::Vector(0.0, 0.0, 1.0));
+        let ts: Vec<f64> = w.intersect(r).iter().map(|i| i.t).collect();
+        assert_eq!(ts, vec![-1.0, -0.5, 0.5]);
+        // and so do the ones before t_min, but the hit is past it
+        let xs = w.intersect(r.with_interval(0.6, f64::INFINITY));
+        let ts: Vec<f64> = xs.iter().map(|i| i.t).collect();
+        assert_eq!(ts, vec![-1.0, -0.5, 0.5, 1.0]);
+        assert_eq!(xs.hit().unwrap().t, 1.0);
+    }
+
     #[test]
     fn test_intersect_world_behind_ray() {
         let mut w = World::default_from_book();
@@ -290,6 +497,22 @@ mod tests {
         assert_eq!(w.is_shadowed(Tuple::Point(-2.0, 2.0, -2.0)), false);
     }
 
+    #[test]
+    fn occlusion_only_counts_blockers_inside_the_range() {
+        let mut w = World::default_from_book();
+        let r = Ray::new(Tuple::Point(0.0, 0.0, -5.0), Tuple::Vector(0.0, 0.0, 1.0));
+        assert!(w.is_occluded(&r.with_interval(0.0, 10.0)));
+        assert!(!w.is_occluded(&r.with_interval(0.0, 3.9)));
+        assert!(!w.is_occluded(&r.with_interval(6.1, 10.0)));
+        // blockers inside groups count too
+        let body = w.bodies.remove(0);
+        w.bodies.clear();
+        assert!(!w.is_occluded(&r));
+        w.groups
+            .push(Group::new(Matrix::Identity(), vec![body.into()]).build());
+        assert!(w.is_occluded(&r));
+    }
+
     #[test]
     fn transparency_factor_works() {
         let s1 = Sphere::new(
@@ -318,4 +541,396 @@ mod tests {
         let result = w.transparency_factor(Tuple::Point(100, 0, 0));
         assert_eq!(result, 0.5 * 0.25);
     }
+
+    #[test]
+    fn dispersive_glass_bends_blue_more_than_red() {
+        let glass = Sphere::new(
+            Matrix::Identity(),
+            Material::Phong(Phong {
+                transparency: 1.0,
+                refractive_index: 1.5,
+                abbe_number: 20.0,
+                ..Default::default()
+            }),
+        );
+        let w = World::new(
+            vec![PointLight::new(Tuple::Point(-10, 10, -10), Color::WHITE())],
+            vec![glass.into()],
+            vec![],
+            5,
+        );
+        let r = Ray::new(Tuple::Point(0.0, 0.5, -5.0), Tuple::Vector(0, 0, 1));
+        let xs = w.intersect(r);
+        let hit = xs.hit().unwrap();
+        let (mu_from, mu_to) = xs.get_mu_shift(hit);
+        let (abbe_from, abbe_to) = xs.get_abbe_shift(hit);
+        let cs = hit
+            .as_computed(mu_from, mu_to)
+            .with_abbe_numbers(abbe_from, abbe_to);
+
+        assert!(cs.is_dispersive());
+        let deviation = |wavelength| {
+            let (mu_from, mu_to) = cs.mu_at(wavelength);
+            let direction = refraction_direction(&cs, mu_from / mu_to).unwrap();
+            direction.normalize().dot(&r.direction).acos()
+        };
+        assert!(deviation(WAVELENGTH_RED) > 0.0);
+        assert!(deviation(WAVELENGTH_RED) < deviation(WAVELENGTH_GREEN));
+        assert!(deviation(WAVELENGTH_GREEN) < deviation(WAVELENGTH_BLUE));
+        let reflectance = cs.schlick_color();
+        assert!(reflectance.red() < reflectance.blue());
+    }
+
+    #[test]
+    fn dispersion_splits_a_ray_only_once() {
+        let glass = |scale: f64| {
+            Sphere::new(
+                Matrix::Scaling(scale, scale, scale),
+                Material::Phong(Phong {
+                    transparency: 1.0,
+                    refractive_index: 1.5,
+                    abbe_number: 20.0,
+                    ..Default::default()
+                }),
+            )
+            .into()
+        };
+        let w = World::new(
+            vec![PointLight::new(Tuple::Point(-10, 10, -10), Color::WHITE())],
+            vec![glass(2.0), glass(1.0)],
+            vec![],
+            5,
+        );
+        // straight through the centre: in and out of both spheres, four refractions per channel
+        let r = Ray::new(Tuple::Point(0, 0, -5), Tuple::Vector(0, 0, 1));
+        let (_, traced) = stats::measure(|| w.color_at(r));
+        assert_eq!(traced.refraction_rays, 3 * 4);
+    }
+
+    #[test]
+    fn missed_rays_and_reflections_see_the_background() {
+        let mirror = Plane::new(
+            Matrix::Identity(),
+            Material::Phong(Phong {
+                pattern: Pattern::Flat(Flat::new(Color::BLACK())),
+                ambient: 0.0,
+                diffuse: 0.0,
+                specular: 0.0,
+                reflectiveness: 1.0,
+                ..Default::default()
+            }),
+        );
+        let w = World::new(
+            vec![PointLight::new(Tuple::Point(-10, 10, -10), Color::WHITE())],
+            vec![mirror.into()],
+            vec![],
+            5,
+        )
+        .with_background(Background::Gradient(VerticalGradient::new(
+            Color::BLUE(),
+            Color::RED(),
+        )));
+        let up = Ray::new(Tuple::Point(0, 1, 0), Tuple::Vector(0, 1, 0));
+        assert_eq!(w.color_at(up), Color::BLUE());
+        let down = Ray::new(Tuple::Point(0, 1, 0), Tuple::Vector(0, -1, 0));
+        assert_eq!(w.color_at(down), Color::BLUE());
+    }
+
+    #[test]
+    fn grazing_reflections_do_not_hit_their_own_surface() {
+        let mirror = Plane::new(
+            Matrix::Identity(),
+            Material::Phong(Phong {
+                reflectiveness: 1.0,
+                ..Default::default()
+            }),
+        );
+        let w = World::new(
+            vec![PointLight::new(Tuple::Point(-10, 10, -10), Color::WHITE())],
+            vec![mirror.into()],
+            vec![],
+            5,
+        );
+        // rounding left the reflection's origin just under the mirror it bounced off
+        let reflected = Ray::new(
+            Tuple::Point(0.0, -1e-9, 0.0),
+            Tuple::Vector(0.0, 0.01, 1.0).normalize(),
+        );
+        let t = w.intersect(reflected).hit().unwrap().t;
+        assert!(t > 0.0 && t < EPSILON);
+        let xs = w.intersect(reflected.with_interval(EPSILON, f64::INFINITY));
+        assert_eq!(xs.count(), 1);
+        assert!(xs.hit().is_none());
+    }
+
+    #[test]
+    fn scene_round_trips_through_encoding() {
+        use crate::{
+            encoding::Encode,
+            group::Group,
+            heightfield::Heightfield,
+            pattern::{Checkers, Perturbed},
+            torus::Torus,
+        };
+        let checkered = Material::Phong(Phong {
+            pattern: Pattern::Perturbed(Perturbed::new(
+                Pattern::Checkers(Checkers::new(
+                    Color::RED(),
+                    Color::BLUE(),
+                    Matrix::Scaling(0.5, 0.5, 0.5),
+                    true,
+                )),
+                0.2,
+            )),
+            reflectiveness: 0.3,
+            ..Default::default()
+        });
+        let sdf = Sdf::new(
+            Matrix::Translation(2.0, 0.0, 0.0),
+            checkered.clone(),
+            DistanceField::cuboid(1.0, 0.5, 0.5)
+                .smooth_union(DistanceField::sphere(0.7).translate(0.0, 1.0, 0.0), 0.2)
+                .twist(0.3),
+        );
+        let terrain = Heightfield::from_fn(4, 3, |x, z| x * z).with_material(checkered);
+        let group = Group::new(
+            Matrix::Translation(0.0, 2.0, 0.0),
+            vec![Body::from(Torus::default()).into()],
+        )
+        .build();
+        let mut world = World::default_from_book()
+            .with_background(Background::Gradient(VerticalGradient::new(
+                Color::BLUE(),
+                Color::RED(),
+            )))
+            .with_environment_light(white_environment_light());
+        world.bodies.extend([sdf.into(), terrain.into()]);
+        world.groups.push(group);
+        world.reflection_limit = 3;
+
+        let bytes = world.to_bytes();
+        let decoded = World::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.bodies, world.bodies);
+        assert_eq!(decoded.reflection_limit, 3);
+        assert_eq!(decoded.to_bytes(), bytes);
+        let ray = Ray::new(Tuple::Point(0.5, 0.2, -5.0), Tuple::Vector(0, 0, 1));
+        assert_eq!(decoded.color_at(ray), world.color_at(ray));
+
+        assert!(World::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    fn white_environment_light() -> EnvironmentLight {
+        let mut image = Canvas::new(16, 8);
+        for y in 0..8 {
+            for x in 0..16 {
+                image.set_color_at_pixel(x, y, Color::WHITE());
+            }
+        }
+        let map = EnvironmentMap::new(
+            ImageTexture::new(image, TextureFilter::Nearest, WrapMode::Repeat),
+            1.0,
+        );
+        EnvironmentLight::new(map, 4000)
+    }
+
+    fn matte_white_sphere() -> Body {
+        Sphere::default()
+            .with_material(Material::Phong(Phong {
+                pattern: Pattern::Flat(Flat::new(Color::WHITE())),
+                ambient: 0.0,
+                diffuse: 1.0,
+                specular: 0.0,
+                ..Default::default()
+            }))
+            .into()
+    }
+
+    #[test]
+    fn uniform_environment_lights_like_a_head_on_white_light() {
+        let w = World::new(vec![], vec![matte_white_sphere()], vec![], 0)
+            .with_environment_light(white_environment_light());
+        let c = w.color_at(Ray::new(Tuple::Point(0, 0, -5), Tuple::Vector(0, 0, 1)));
+        for channel in [c.red(), c.green(), c.blue()] {
+            assert!((channel - 1.0).abs() < 0.05, "{:?}", c);
+        }
+    }
+
+    #[test]
+    fn environment_light_is_shadowed_by_geometry() {
+        let wall = Plane::new(
+            Matrix::Translation(0.0, 0.0, -1.5) * Matrix::rotation_X(PI / 2.0),
+            Material::Phong(Phong::default()),
+        );
+        let w = World::new(vec![], vec![matte_white_sphere(), wall.into()], vec![], 0)
+            .with_environment_light(white_environment_light());
+        let xs = w.intersect(Ray::new(
+            Tuple::Point(0.0, 0.0, -1.2),
+            Tuple::Vector(0, 0, 1),
+        ));
+        let comps = xs.hit().unwrap().as_computed(1.0, 1.0);
+        assert_eq!(w.surface_color_at(&comps), Color::BLACK());
+    }
+
+    #[test]
+    fn environment_light_shines_through_glass_dimmed_by_its_transparency() {
+        let pane = Plane::new(
+            Matrix::Translation(0.0, 0.0, -1.5) * Matrix::rotation_X(PI / 2.0),
+            Material::Phong(Phong {
+                transparency: 0.5,
+                ..Default::default()
+            }),
+        );
+        let w = World::new(vec![], vec![matte_white_sphere(), pane.into()], vec![], 0)
+            .with_environment_light(white_environment_light());
+        let xs = w.intersect(Ray::new(
+            Tuple::Point(0.0, 0.0, -1.2),
+            Tuple::Vector(0, 0, 1),
+        ));
+        let comps = xs.hit().unwrap().as_computed(1.0, 1.0);
+        let c = w.surface_color_at(&comps);
+        for channel in [c.red(), c.green(), c.blue()] {
+            assert!((channel - 0.5).abs() < 0.05, "{:?}", c);
+        }
+    }
+
+    #[test]
+    fn sdf_bodies_shade_like_their_analytic_twins() {
+        let analytic = World::default_from_book();
+        let mut traced = World::default_from_book();
+        for body in traced.bodies.iter_mut() {
+            *body = Sdf::new(
+                body.transform(),
+                body.material().clone(),
+                DistanceField::sphere(1.0),
+            )
+            .into();
+        }
+        for ray in [
+            Ray::new(Tuple::Point(0.0, 0.0, -5.0), Tuple::Vector(0, 0, 1)),
+            Ray::new(Tuple::Point(0.3, 0.4, -5.0), Tuple::Vector(0, 0, 1)),
+            Ray::new(Tuple::Point(0.0, 0.0, 0.75), Tuple::Vector(0, 0, -1)),
+        ] {
+            let expected = analytic.color_at(ray);
+            let actual = traced.color_at(ray);
+            for (a, e) in [
+                (actual.red(), expected.red()),
+                (actual.green(), expected.green()),
+                (actual.blue(), expected.blue()),
+            ] {
+                assert!((a - e).abs() < 1e-3, "{actual:?} != {expected:?}");
+            }
+        }
+    }
+
+    mod allocations {
+        use std::{
+            alloc::{GlobalAlloc, Layout, System},
+            cell::Cell,
+        };
+
+        thread_local! {
+            static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+        }
+
+        /// Counts allocations per thread, so tests running in parallel don't disturb each other.
+        struct CountingAllocator;
+
+        unsafe impl GlobalAlloc for CountingAllocator {
+            unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+                let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+                System.alloc(layout)
+            }
+
+            unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+                System.dealloc(ptr, layout)
+            }
+        }
+
+        #[global_allocator]
+        static GLOBAL: CountingAllocator = CountingAllocator;
+
+        pub fn count() -> usize {
+            ALLOCATIONS.with(|count| count.get())
+        }
+    }
+
+    #[test]
+    fn tracing_rays_does_not_allocate() {
+        let mut w = World::default_from_book();
+        w.reflection_limit = 5;
+        w.add_body(
+            Plane::default()
+                .with_material(Material::Phong(Phong::default().with_reflectiveness(0.5)))
+                .into(),
+        );
+        w.add_body(
+            Sphere::new(
+                Matrix::Translation(1.5, 0.5, -1.5) * Matrix::Scaling(0.5, 0.5, 0.5),
+                Material::Phong(Phong {
+                    transparency: 1.0,
+                    reflectiveness: 0.9,
+                    refractive_index: 1.5,
+                    ..Default::default()
+                }),
+            )
+            .into(),
+        );
+        // the solver based and grid traversing bodies, placed where the rays below reach them
+        w.add_body(
+            Torus::default()
+                .with_transform(
+                    Matrix::Translation(-0.5, 0.3, -3.0)
+                        * Matrix::Scaling(0.25, 0.25, 0.25)
+                        * Matrix::rotation_X(PI / 2.0),
+                )
+                .into(),
+        );
+        w.add_body(
+            Quadric::ellipsoid(0.2, 0.3, 0.2)
+                .with_transform(Matrix::Translation(0.4, 0.3, -3.0))
+                .into(),
+        );
+        w.add_body(
+            Cone::default()
+                .with_caps(true)
+                .with_transform(
+                    Matrix::Translation(-0.2, 0.0, -2.5) * Matrix::Scaling(0.2, 0.4, 0.2),
+                )
+                .into(),
+        );
+        w.add_body(
+            Heightfield::from_fn(8, 8, |x, z| 0.3 * x * z)
+                .with_transform(
+                    Matrix::Translation(0.0, 0.0, -3.8) * Matrix::Scaling(0.6, 1.0, 0.6),
+                )
+                .into(),
+        );
+        let rays: Vec<Ray> = (0..100)
+            .map(|i| {
+                let target = Tuple::Point(
+                    (i % 10) as f64 * 0.4 - 2.0,
+                    (i / 10) as f64 * 0.4 - 2.0,
+                    0.0,
+                );
+                let origin = Tuple::Point(0, 1, -5);
+                Ray::new(origin, (target - origin).normalize())
+            })
+            .collect();
+
+        let before = allocations::count();
+        let colors: Color = rays
+            .iter()
+            .fold(Color::BLACK(), |acc, ray| acc + w.color_at(*ray));
+        assert_eq!(allocations::count() - before, 0);
+        assert!(colors.red() > 0.0);
+        // make sure the counter really sees allocations
+        std::hint::black_box(Box::new(0));
+        assert_eq!(allocations::count() - before, 1);
+        for body in w.bodies.iter() {
+            assert!(
+                rays.iter().any(|ray| body.intersect(ray).count() > 0),
+                "no ray reaches {body:?}"
+            );
+        }
+    }
 }